@@ -0,0 +1,65 @@
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+use crate::{operation::CallResult, rpc_error::INVALID_PARAMS, workload::Workload};
+
+/// Lamports the bank credits the workload's fee payer with
+const PAYER_LAMPORTS: u64 = 1_000_000_000_000;
+
+/// An in-process bank simulating transactions through `BanksClient`, the
+/// floor cost of a simulation with no network or rpc server in the way
+pub struct BankClient {
+    context: ProgramTestContext,
+}
+
+impl BankClient {
+    /// Starts a bank with the builtin and SPL programs, funding the fee payer
+    /// of `workload`'s transactions. Must run on the runtime that will drive
+    /// the simulations, which the bank's server is spawned onto.
+    pub async fn start(workload: &dyn Workload) -> BankClient {
+        let mut program_test = ProgramTest::default();
+        // `ProgramTest::default()` turns on debug logging for every
+        // instruction the bank runs, which this resets to `RUST_LOG`
+        solana_logger::setup();
+        if let Some(payer) = workload.build().message.account_keys.first() {
+            let account = Account::new(PAYER_LAMPORTS, 0, &system_program::id());
+            program_test.add_account(*payer, account);
+        }
+        BankClient {
+            context: program_test.start_with_context().await,
+        }
+    }
+
+    /// Simulates `transaction` at the bank's latest blockhash, since the one
+    /// it was built with is from the endpoint's cluster. Simulation doesn't
+    /// verify signatures, so the stale ones don't matter.
+    pub async fn simulate(&self, mut transaction: Transaction) -> CallResult {
+        transaction.message.recent_blockhash = self.context.last_blockhash;
+        let simulation = self
+            .context
+            .banks_client
+            .clone()
+            .simulate_transaction(transaction)
+            .await
+            .map_err(|err| RpcError::RpcRequestError(format!("banks client: {err}")))?;
+        match simulation.result {
+            // Reported the way the rpc rejects it, so the same outcome is
+            // expected of every mode
+            Some(Err(TransactionError::SanitizeFailure)) => Err(RpcError::RpcResponseError {
+                code: INVALID_PARAMS,
+                message: "invalid transaction: Transaction failed to sanitize accounts offsets \
+                          correctly"
+                    .to_string(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into()),
+            Some(Err(err)) => Ok(Some(err)),
+            Some(Ok(())) | None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::Mode,
+    stats::{Iteration, RunStats},
+};
+
+/// Significance level below which a difference counts as a change
+const ALPHA: f64 = 0.05;
+
+/// Checks `name` can be saved as a file directly inside the baseline directory
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().any(std::path::is_separator) {
+        Err(format!(
+            "expected a baseline name without path separators; got {name:?}"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Latency samples and per-iteration throughput of a run, saved under a name
+/// so later runs can be compared against it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    pub name: String,
+    pub modes: Vec<BaselineMode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaselineMode {
+    pub endpoint: String,
+    pub mode: Mode,
+    /// Distinct latencies in micros and how many requests took each
+    pub latency_micros: Vec<(u64, u64)>,
+    pub first_try_latency_micros: Vec<(u64, u64)>,
+    /// Throughput of each iteration
+    pub throughput: Vec<f64>,
+}
+
+impl Baseline {
+    pub fn new(name: &str) -> Baseline {
+        Baseline {
+            name: name.to_string(),
+            modes: vec![],
+        }
+    }
+
+    pub fn push(&mut self, endpoint: &str, mode: Mode, stats: &RunStats, iterations: &[Iteration]) {
+        self.modes.push(BaselineMode {
+            endpoint: endpoint.to_string(),
+            mode,
+            latency_micros: samples(&stats.latencies),
+            first_try_latency_micros: samples(&stats.first_try),
+            throughput: iterations.iter().map(|i| i.throughput).collect(),
+        });
+    }
+
+    fn path(dir: &Path, name: &str) -> io::Result<PathBuf> {
+        check_name(name).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        Ok(dir.join(format!("{name}.json")))
+    }
+
+    pub fn load(dir: &Path, name: &str) -> io::Result<Baseline> {
+        let file = File::open(Baseline::path(dir, name)?)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Writes the baseline into `dir`, replacing any with the same name
+    pub fn save(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = Baseline::path(dir, &self.name)?;
+        fs::create_dir_all(dir)?;
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(path)
+    }
+
+    /// Compares each mode of `current` with the same mode against the
+    /// endpoint in the same position in this baseline. Endpoints are matched
+    /// by position so runs against fresh mock servers stay comparable.
+    pub fn compare(&self, current: &Baseline) -> Vec<Comparison> {
+        let baseline_endpoints = self.endpoints();
+        let current_endpoints = current.endpoints();
+        current
+            .modes
+            .iter()
+            .filter_map(|now| {
+                let position = current_endpoints.iter().position(|e| *e == now.endpoint)?;
+                let endpoint = baseline_endpoints.get(position)?;
+                let then = self
+                    .modes
+                    .iter()
+                    .find(|then| then.endpoint == *endpoint && then.mode == now.mode)?;
+                Some(Comparison {
+                    endpoint: now.endpoint.clone(),
+                    mode: now.mode,
+                    metrics: vec![
+                        Metric::lower_is_faster(
+                            "latency p50",
+                            &then.latency_micros,
+                            &now.latency_micros,
+                        ),
+                        Metric::lower_is_faster(
+                            "first try p50",
+                            &then.first_try_latency_micros,
+                            &now.first_try_latency_micros,
+                        ),
+                        Metric::higher_is_faster("req/s", &then.throughput, &now.throughput),
+                    ],
+                })
+            })
+            .collect()
+    }
+
+    /// Endpoints in the order they were benchmarked
+    fn endpoints(&self) -> Vec<&str> {
+        let mut endpoints: Vec<&str> = vec![];
+        for mode in &self.modes {
+            if !endpoints.contains(&mode.endpoint.as_str()) {
+                endpoints.push(&mode.endpoint);
+            }
+        }
+        endpoints
+    }
+}
+
+/// How one mode changed since the baseline
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub endpoint: String,
+    pub mode: Mode,
+    pub metrics: Vec<Metric>,
+}
+
+/// Medians of one metric then and now, with the Mann-Whitney U test verdict
+#[derive(Debug, Serialize)]
+pub struct Metric {
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+    /// Relative change of the median
+    pub change: f64,
+    /// Two-sided p-value, if there were enough samples to test
+    pub p_value: Option<f64>,
+    pub verdict: Verdict,
+}
+
+impl Metric {
+    pub fn lower_is_faster(
+        metric: &'static str,
+        then: &[(u64, u64)],
+        now: &[(u64, u64)],
+    ) -> Metric {
+        let weighted = |samples: &[(u64, u64)]| {
+            samples
+                .iter()
+                .map(|&(value, count)| (value as f64, count))
+                .collect::<Vec<_>>()
+        };
+        Metric::new(metric, &weighted(then), &weighted(now), false)
+    }
+
+    pub fn higher_is_faster(metric: &'static str, then: &[f64], now: &[f64]) -> Metric {
+        let weighted = |samples: &[f64]| samples.iter().map(|&v| (v, 1)).collect::<Vec<_>>();
+        Metric::new(metric, &weighted(then), &weighted(now), true)
+    }
+
+    fn new(
+        metric: &'static str,
+        then: &[(f64, u64)],
+        now: &[(f64, u64)],
+        higher_is_faster: bool,
+    ) -> Metric {
+        let (baseline, current) = (median(then), median(now));
+        let test = mann_whitney_u(now, then);
+        let verdict = match test {
+            None => Verdict::Untested,
+            Some(test) if test.p_value >= ALPHA => Verdict::NoChange,
+            // A positive z means current values rank higher than the baseline's
+            Some(test) if (test.z > 0.0) == higher_is_faster => Verdict::Faster,
+            Some(_) => Verdict::Slower,
+        };
+        Metric {
+            metric,
+            baseline,
+            current,
+            change: current / baseline - 1.0,
+            p_value: test.map(|test| test.p_value),
+            verdict,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verdict {
+    Faster,
+    Slower,
+    NoChange,
+    /// Too few samples on either side to test
+    Untested,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Faster => write!(f, "faster"),
+            Verdict::Slower => write!(f, "slower"),
+            Verdict::NoChange => write!(f, "no significant change"),
+            Verdict::Untested => write!(f, "too few samples"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UTest {
+    /// Positive if `a` tends to rank above `b`
+    pub z: f64,
+    /// Two-sided
+    pub p_value: f64,
+}
+
+/// Mann-Whitney U test of whether `a` tends to rank above or below `b`,
+/// using the normal approximation with tie correction. Samples are
+/// `(value, count)` pairs.
+pub fn mann_whitney_u(a: &[(f64, u64)], b: &[(f64, u64)]) -> Option<UTest> {
+    let n_a = a.iter().map(|&(_, count)| count).sum::<u64>() as f64;
+    let n_b = b.iter().map(|&(_, count)| count).sum::<u64>() as f64;
+    if n_a < 2.0 || n_b < 2.0 {
+        return None;
+    }
+
+    let mut pooled = a
+        .iter()
+        .map(|&(value, count)| (value, count, 0))
+        .chain(b.iter().map(|&(value, count)| (value, 0, count)))
+        .collect::<Vec<_>>();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Sum of `a`'s ranks, giving tied values their average rank
+    let (mut rank_sum, mut ties, mut ranked) = (0.0, 0.0, 0.0);
+    let mut i = 0;
+    while i < pooled.len() {
+        let (mut tied_a, mut tied) = (0, 0);
+        let value = pooled[i].0;
+        while i < pooled.len() && pooled[i].0 == value {
+            tied_a += pooled[i].1;
+            tied += pooled[i].1 + pooled[i].2;
+            i += 1;
+        }
+        let tied = tied as f64;
+        let average_rank = ranked + (tied + 1.0) / 2.0;
+        rank_sum += average_rank * tied_a as f64;
+        ties += tied.powi(3) - tied;
+        ranked += tied;
+    }
+
+    let n = n_a + n_b;
+    let u = rank_sum - n_a * (n_a + 1.0) / 2.0;
+    let mean = n_a * n_b / 2.0;
+    let variance = n_a * n_b / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        // Every sample is identical
+        return Some(UTest {
+            z: 0.0,
+            p_value: 1.0,
+        });
+    }
+    // Continuity correction towards the mean, without overshooting it when
+    // the rank sums only differ by rounding
+    let shift = u - mean;
+    let z = shift.signum() * (shift.abs() - 0.5).max(0.0) / variance.sqrt();
+    Some(UTest {
+        z,
+        p_value: (2.0 * (1.0 - normal_cdf(z.abs()))).clamp(0.0, 1.0),
+    })
+}
+
+/// Standard normal cdf, using the Abramowitz and Stegun 7.1.26 erf
+/// approximation (accurate to about 1e-7)
+pub fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+/// Median of `(value, count)` samples
+pub fn median(samples: &[(f64, u64)]) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let total = sorted.iter().map(|&(_, count)| count).sum::<u64>();
+    let middle = total.div_ceil(2);
+    let mut seen = 0;
+    for (value, count) in sorted {
+        seen += count;
+        if seen >= middle {
+            return value;
+        }
+    }
+    f64::NAN
+}
+
+fn samples(histogram: &Histogram<u64>) -> Vec<(u64, u64)> {
+    histogram
+        .iter_recorded()
+        .map(|value| (value.value_iterated_to(), value.count_at_value()))
+        .collect()
+}
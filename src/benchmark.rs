@@ -0,0 +1,564 @@
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc, time::Duration};
+
+use rayon::ThreadPool;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient as AsyncRpcClient, rpc_client::RpcClient,
+    rpc_config::RpcSimulateTransactionConfig,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
+};
+use tokio::runtime::Runtime;
+use tracing::info_span;
+
+#[cfg(feature = "banks")]
+use crate::banks::BankClient;
+use crate::{
+    cli::{BenchArgs, ConcurrencyArgs, Mode, RetryArgs},
+    fault::Faults,
+    mock_server::MockServer,
+    operation::{AsyncClient, Method, Operation},
+    outcome::{Expect, Expectation},
+    rate_limit::{RateLimiter, MIN_RATE},
+    raw_client::RawClient,
+    recording::{Recorder, Recording, RecordingProxy, ReplayServer},
+    retry::RetryPolicy,
+    runner::{build_runtime, run_async, run_batch, run_sync, RunOptions},
+    stats::{Iteration, RunStats},
+    workload::{Empty, Workload},
+};
+
+/// Stats of one client mode against one endpoint
+pub struct ModeRun {
+    pub endpoint: String,
+    pub mode: Mode,
+    /// Combined across iterations
+    pub stats: RunStats,
+    pub iterations: Vec<Iteration>,
+}
+
+/// One rpc call benchmarked against a list of endpoints in a list of client
+/// modes, along with the thread pool and runtime driving the clients
+pub struct Benchmark {
+    endpoints: Vec<String>,
+    servers: Servers,
+    modes: Vec<Mode>,
+    warmup: u64,
+    iterations: u32,
+    batch_size: u64,
+    timeout: Duration,
+    options: RunOptions,
+    pool: ThreadPool,
+    runtime: Runtime,
+}
+
+impl Benchmark {
+    /// Starts configuring a benchmark. Everything but the endpoints defaults
+    /// to what the `bench` subcommand uses.
+    pub fn builder() -> BenchmarkBuilder {
+        let args = BenchArgs::default();
+        BenchmarkBuilder {
+            endpoints: vec![],
+            servers: Servers::default(),
+            modes: vec![],
+            method: args.method.method,
+            addresses: args.method.addresses,
+            commitment: None,
+            simulate: RpcSimulateTransactionConfig::default(),
+            workload: Arc::new(Empty),
+            expect: args.expect,
+            expect_code: args.expect_code,
+            requests: args.requests,
+            warmup: args.warmup,
+            iterations: args.iterations,
+            batch_size: args.batch_size,
+            timeout: Duration::from_secs(args.timeout),
+            rate_limit: None,
+            arrival_rate: None,
+            retry: args.retry,
+            concurrency: args.concurrency,
+        }
+    }
+
+    /// Outcome every request is checked against
+    pub fn expectation(&self) -> Expectation {
+        self.options.expectation
+    }
+
+    /// Endpoints runs are reported under, including any embedded mocks or
+    /// replay server
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    /// Requests measured per mode and iteration
+    pub fn requests(&self) -> u64 {
+        self.options.requests
+    }
+
+    /// Runs every mode against every endpoint, one at a time so they don't
+    /// compete for the client's cpu and network
+    pub fn run(&self) -> Vec<ModeRun> {
+        let warmup = RunOptions {
+            requests: self.warmup,
+            ..self.options.clone()
+        };
+        let (pool, runtime) = (&self.pool, &self.runtime);
+        let mut runs = vec![];
+        for url in &self.endpoints {
+            for &mode in &self.modes {
+                let _span = info_span!("mode", endpoint = %url, mode = mode.label()).entered();
+                let target = self.servers.target(url);
+                // One client per mode, kept across warmup and iterations so its
+                // connections are reused
+                let run: Box<dyn Fn(&RunOptions) -> RunStats> = match mode {
+                    Mode::Sync => {
+                        let client = Arc::new(RpcClient::new_with_timeout(target, self.timeout));
+                        Box::new(move |options| run_sync(pool, Arc::clone(&client), options))
+                    }
+                    Mode::Async => {
+                        let client = AsyncRpcClient::new_with_timeout(target, self.timeout);
+                        let client = Arc::new(AsyncClient::Rpc(client));
+                        Box::new(move |options| run_async(runtime, Arc::clone(&client), options))
+                    }
+                    Mode::Raw => {
+                        let client = RawClient::new_with_timeout(target, self.timeout);
+                        let client = Arc::new(AsyncClient::Raw(client));
+                        Box::new(move |options| run_async(runtime, Arc::clone(&client), options))
+                    }
+                    Mode::Batch => {
+                        let client = Arc::new(RawClient::new_with_timeout(target, self.timeout));
+                        let batch_size = self.batch_size;
+                        Box::new(move |options| {
+                            run_batch(runtime, Arc::clone(&client), batch_size, options)
+                        })
+                    }
+                    #[cfg(feature = "banks")]
+                    Mode::Banks => {
+                        let workload = self.options.operation.workload.as_ref();
+                        let client = runtime.block_on(BankClient::start(workload));
+                        let client = Arc::new(AsyncClient::Bank(Box::new(client)));
+                        Box::new(move |options| run_async(runtime, Arc::clone(&client), options))
+                    }
+                    #[cfg(not(feature = "banks"))]
+                    Mode::Banks => unreachable!("rejected by the builder"),
+                };
+                if warmup.requests > 0 {
+                    run(&warmup);
+                }
+                let mut iterations = vec![];
+                let mut stats = run(&self.options);
+                iterations.push(Iteration::of(&stats));
+                for _ in 1..self.iterations {
+                    let iteration = run(&self.options);
+                    iterations.push(Iteration::of(&iteration));
+                    stats.absorb(iteration);
+                }
+                runs.push(ModeRun {
+                    endpoint: url.clone(),
+                    mode,
+                    stats,
+                    iterations,
+                });
+            }
+        }
+        runs
+    }
+}
+
+/// Configures a [`Benchmark`]
+pub struct BenchmarkBuilder {
+    endpoints: Vec<String>,
+    servers: Servers,
+    modes: Vec<Mode>,
+    method: Method,
+    addresses: Vec<Pubkey>,
+    commitment: Option<CommitmentConfig>,
+    simulate: RpcSimulateTransactionConfig,
+    workload: Arc<dyn Workload>,
+    expect: Expect,
+    expect_code: Option<i64>,
+    requests: u64,
+    warmup: u64,
+    iterations: u32,
+    batch_size: u64,
+    timeout: Duration,
+    rate_limit: Option<(f64, u32)>,
+    arrival_rate: Option<f64>,
+    retry: RetryArgs,
+    concurrency: ConcurrencyArgs,
+}
+
+impl BenchmarkBuilder {
+    /// Configures a benchmark the way the `bench` subcommand does. Starts
+    /// the embedded mocks, replay server and recording proxies asked for,
+    /// which run until the benchmark is dropped, then reads the payer keypair
+    /// and fetches a recent blockhash from the first endpoint if the workload
+    /// needs them. Fails if any of these can't be had.
+    pub fn from_args(args: &BenchArgs) -> io::Result<BenchmarkBuilder> {
+        let (servers, endpoints) = Servers::start(args)?;
+        let workload = match endpoints.first() {
+            Some(url) => build_workload(args, &servers.target(url))?,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no endpoints to benchmark",
+                ))
+            }
+        };
+        // An arrival rate sustained for a duration replaces the request count
+        let requests = match (args.load.arrival_rate, args.load.duration) {
+            (Some(rate), Some(duration)) => (rate * duration).ceil() as u64,
+            _ => args.requests,
+        };
+        let commitment = args.method.commitment_config();
+        let mut builder = Benchmark::builder()
+            .endpoints(endpoints)
+            .modes(args.modes.iter().copied())
+            .method(args.method.method)
+            .addresses(args.method.addresses.clone())
+            .simulate(args.simulate.config(commitment))
+            .workload(workload)
+            .expect(args.expect)
+            .requests(requests)
+            .warmup(args.warmup)
+            .iterations(args.iterations)
+            .batch_size(args.batch_size)
+            .timeout(Duration::from_secs(args.timeout))
+            .retry(args.retry.clone())
+            .concurrency(args.concurrency.clone());
+        // Passed along as is, since `--expect auto` also takes a code
+        builder.expect_code = args.expect_code;
+        builder.servers = servers;
+        if let Some(commitment) = commitment {
+            builder = builder.commitment(commitment);
+        }
+        if let Some(rps) = args.rps {
+            builder = builder.rate_limit(rps, args.burst);
+        }
+        if let Some(rate) = args.load.arrival_rate {
+            builder = builder.arrival_rate(rate);
+        }
+        Ok(builder)
+    }
+
+    /// Adds an endpoint url
+    pub fn endpoint(mut self, url: impl Into<String>) -> BenchmarkBuilder {
+        self.endpoints.push(url.into());
+        self
+    }
+
+    pub fn endpoints(mut self, urls: impl IntoIterator<Item = String>) -> BenchmarkBuilder {
+        self.endpoints.extend(urls);
+        self
+    }
+
+    /// Adds a client mode. Synchronous and asynchronous are run if none are
+    /// added.
+    pub fn mode(mut self, mode: Mode) -> BenchmarkBuilder {
+        self.modes.push(mode);
+        self
+    }
+
+    pub fn modes(mut self, modes: impl IntoIterator<Item = Mode>) -> BenchmarkBuilder {
+        self.modes.extend(modes);
+        self
+    }
+
+    pub fn method(mut self, method: Method) -> BenchmarkBuilder {
+        self.method = method;
+        self
+    }
+
+    /// Accounts queried by getAccountInfo, getBalance (first only) and
+    /// getMultipleAccounts
+    pub fn addresses(mut self, addresses: Vec<Pubkey>) -> BenchmarkBuilder {
+        self.addresses = addresses;
+        self
+    }
+
+    /// Bank commitment to query, also used by simulations that don't set
+    /// their own
+    pub fn commitment(mut self, commitment: CommitmentConfig) -> BenchmarkBuilder {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    /// Options passed to `simulateTransaction`
+    pub fn simulate(mut self, config: RpcSimulateTransactionConfig) -> BenchmarkBuilder {
+        self.simulate = config;
+        self
+    }
+
+    /// Builds the transactions to simulate and messages to price
+    pub fn workload(mut self, workload: Arc<dyn Workload>) -> BenchmarkBuilder {
+        self.workload = workload;
+        self
+    }
+
+    pub fn expect(mut self, expect: Expect) -> BenchmarkBuilder {
+        self.expect = expect;
+        self
+    }
+
+    /// Expects every request to fail with this JSON-RPC error code
+    pub fn expect_code(mut self, code: i64) -> BenchmarkBuilder {
+        self.expect = Expect::RpcError;
+        self.expect_code = Some(code);
+        self
+    }
+
+    /// Requests measured per mode and iteration
+    pub fn requests(mut self, requests: u64) -> BenchmarkBuilder {
+        self.requests = requests;
+        self
+    }
+
+    /// Requests sent per mode before measuring
+    pub fn warmup(mut self, requests: u64) -> BenchmarkBuilder {
+        self.warmup = requests;
+        self
+    }
+
+    pub fn iterations(mut self, iterations: u32) -> BenchmarkBuilder {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Calls packed into each request by [`Mode::Batch`]
+    pub fn batch_size(mut self, batch_size: u64) -> BenchmarkBuilder {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> BenchmarkBuilder {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Paces requests to `requests_per_second` on average, with bursts of up
+    /// to `burst`
+    pub fn rate_limit(mut self, requests_per_second: f64, burst: u32) -> BenchmarkBuilder {
+        self.rate_limit = Some((requests_per_second, burst));
+        self
+    }
+
+    /// Sends requests open-loop at this rate
+    pub fn arrival_rate(mut self, requests_per_second: f64) -> BenchmarkBuilder {
+        self.arrival_rate = Some(requests_per_second);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryArgs) -> BenchmarkBuilder {
+        self.retry = retry;
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: ConcurrencyArgs) -> BenchmarkBuilder {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Builds the rayon pool and tokio runtime driving the clients
+    pub fn build(self) -> io::Result<Benchmark> {
+        if self.endpoints.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no endpoints to benchmark",
+            ));
+        }
+        if self.iterations == 0 || self.batch_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "iterations and batch size must be at least 1",
+            ));
+        }
+        if self.concurrency.rayon_threads == 0
+            || self.concurrency.worker_threads == 0
+            || self.concurrency.max_in_flight == Some(0)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "threads and requests in flight must be at least 1",
+            ));
+        }
+        let rates = self.rate_limit.map(|(rps, _)| rps).into_iter();
+        if !rates
+            .chain(self.arrival_rate)
+            .all(|rate| rate.is_finite() && rate >= MIN_RATE)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("rates must be at least {MIN_RATE} requests per second"),
+            ));
+        }
+        if self.method.queries_accounts() && self.addresses.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} needs at least one address", self.method),
+            ));
+        }
+        if self.modes.contains(&Mode::Banks) {
+            if !cfg!(feature = "banks") {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the banks mode needs tx_sim built with the `banks` feature",
+                ));
+            }
+            if self.method != Method::SimulateTransaction {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the banks mode only simulates transactions",
+                ));
+            }
+        }
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.concurrency.rayon_threads)
+            .thread_name(|i| format!("rayon-{i}"))
+            .build()
+            .map_err(io::Error::other)?;
+        let runtime = build_runtime(&self.concurrency)?;
+
+        let operation = Operation {
+            method: self.method,
+            addresses: self.addresses,
+            commitment: self.commitment.unwrap_or_default(),
+            simulate: RpcSimulateTransactionConfig {
+                commitment: self.simulate.commitment.or(self.commitment),
+                ..self.simulate
+            },
+            workload: self.workload,
+        };
+        let expectation = Expectation::new(self.expect, self.expect_code, operation.rejected());
+        let modes = if self.modes.is_empty() {
+            BenchArgs::default().modes
+        } else {
+            self.modes
+        };
+        Ok(Benchmark {
+            endpoints: self.endpoints,
+            servers: self.servers,
+            modes,
+            warmup: self.warmup,
+            iterations: self.iterations,
+            batch_size: self.batch_size,
+            timeout: self.timeout,
+            options: RunOptions {
+                requests: self.requests,
+                max_in_flight: self.concurrency.max_in_flight,
+                limiter: self
+                    .rate_limit
+                    .map(|(rps, burst)| Arc::new(RateLimiter::new(rps, burst))),
+                retry: Arc::new(RetryPolicy::new(&self.retry)),
+                arrival_rate: self.arrival_rate,
+                operation: Arc::new(operation),
+                expectation,
+            },
+            pool,
+            runtime,
+        })
+    }
+
+    /// Builds the benchmark and runs it
+    pub fn run(self) -> io::Result<Vec<ModeRun>> {
+        Ok(self.build()?.run())
+    }
+}
+
+/// Servers a benchmark runs against in place of its endpoints, or forwards
+/// through in front of them. They shut down when dropped.
+#[derive(Default)]
+struct Servers {
+    mocks: Vec<MockServer>,
+    replay: Option<ReplayServer>,
+    /// Recording proxies by the endpoint they forward to
+    proxies: HashMap<String, RecordingProxy>,
+}
+
+impl Servers {
+    /// Starts the servers `args` asks for, returning them along with the
+    /// endpoints to benchmark: the mocks or replay server if any, otherwise
+    /// `args.urls`
+    fn start(args: &BenchArgs) -> io::Result<(Servers, Vec<String>)> {
+        let loopback = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut servers = Servers::default();
+        let mut endpoints = args.urls.clone();
+        if let Some(count) = args.mock {
+            let faults = Faults::new(&args.faults)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            for _ in 0..count {
+                servers
+                    .mocks
+                    .push(MockServer::start_with_faults(loopback, faults.clone())?);
+            }
+            endpoints = servers.mocks.iter().map(MockServer::url).collect();
+        }
+        if let Some(path) = &args.record.replay {
+            let recording = Recording::load(path).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("failed to load recording {}: {err}", path.display()),
+                )
+            })?;
+            let server = ReplayServer::start(loopback, recording, args.record.replay_latency)?;
+            endpoints = vec![server.url()];
+            servers.replay = Some(server);
+        }
+        if let Some(path) = &args.record.record {
+            let recorder = Recorder::create(path).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("failed to create recording {}: {err}", path.display()),
+                )
+            })?;
+            let recorder = Arc::new(recorder);
+            for url in &endpoints {
+                let proxy = RecordingProxy::start(
+                    loopback,
+                    url.clone(),
+                    Duration::from_secs(args.timeout),
+                    Arc::clone(&recorder),
+                )?;
+                servers.proxies.insert(url.clone(), proxy);
+            }
+        }
+        Ok((servers, endpoints))
+    }
+
+    /// Url requests to `endpoint` are sent to, which is its recording proxy
+    /// if it has one
+    fn target(&self, endpoint: &str) -> String {
+        self.proxies
+            .get(endpoint)
+            .map_or_else(|| endpoint.to_string(), RecordingProxy::url)
+    }
+}
+
+/// Builds the workload selected on the command line, fetching a recent
+/// blockhash from `url` if its transactions need one
+fn build_workload(args: &BenchArgs, url: &str) -> io::Result<Arc<dyn Workload>> {
+    let payer = match &args.workload.payer {
+        Some(path) => read_keypair_file(path).map_err(|err| {
+            io::Error::other(format!(
+                "failed to read payer keypair {}: {err}",
+                path.display()
+            ))
+        })?,
+        None => Keypair::new(),
+    };
+    let blockhash = if args.workload.workload.needs_blockhash() {
+        RpcClient::new(url)
+            .get_latest_blockhash()
+            .map_err(|err| io::Error::other(format!("failed to fetch recent blockhash: {err}")))?
+    } else {
+        Hash::default()
+    };
+    Ok(Arc::from(args.workload.workload.into_workload(
+        payer,
+        args.workload.instructions,
+        blockhash,
+    )))
+}
@@ -0,0 +1,738 @@
+use std::{fmt, net::SocketAddr, path::PathBuf};
+
+use clap::{Args, FromArgMatches, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize, Serializer};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{
+    RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::{
+    baseline, fault::Latency, operation::Method, outcome::Expect, pubsub::Subscription,
+    rate_limit::MIN_RATE, retry::Transient, workload::WorkloadKind,
+};
+
+/// Public solana mainnet beta endpoint
+pub const MAINNET_BETA_ENDPOINT: &str = "https://api.mainnet-beta.solana.com";
+
+/// Public solana devnet endpoint
+pub const DEVNET_ENDPOINT: &str = "https://api.devnet.solana.com";
+
+/// Public solana testnet endpoint
+pub const TESTNET_ENDPOINT: &str = "https://api.testnet.solana.com";
+
+/// Default local validator endpoint
+pub const LOCALHOST_ENDPOINT: &str = "http://localhost:8899";
+
+#[derive(Debug, Parser)]
+#[command(
+    author,
+    version,
+    about = "Compare blocking and nonblocking solana rpc clients"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+// Parsed once at startup, so the size difference doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Simulate transactions against an rpc endpoint
+    Bench(BenchArgs),
+    /// Run the benchmark described by a scenario file
+    Run(RunArgs),
+    /// Measure pubsub subscribe latency and notification timing
+    Pubsub(PubsubArgs),
+    /// Run the mock JSON-RPC and pubsub servers until interrupted
+    Serve(ServeArgs),
+    /// Serve the responses recorded by `bench --record` until interrupted
+    Replay(ReplayArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    /// Rpc endpoint urls or cluster monikers (mainnet, devnet, testnet, localhost),
+    /// comma separated or repeated to compare several endpoints
+    #[arg(
+        short = 'u',
+        long = "url",
+        value_name = "URL",
+        value_delimiter = ',',
+        default_value = "mainnet",
+        value_parser = parse_endpoint,
+        conflicts_with = "replay"
+    )]
+    pub urls: Vec<String>,
+
+    /// Number of requests to send per mode
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 16,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub requests: u64,
+
+    /// Requests sent per mode before measuring, to set up connections
+    #[arg(long, default_value_t = 0)]
+    pub warmup: u64,
+
+    /// Times to repeat each mode's measured requests
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub iterations: u32,
+
+    /// Comma separated clients to benchmark, in order
+    #[arg(
+        short,
+        long = "mode",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Mode::Sync, Mode::Async]
+    )]
+    pub modes: Vec<Mode>,
+
+    /// Calls packed into each JSON-RPC batch by the batch mode
+    #[arg(
+        long,
+        default_value_t = 8,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub batch_size: u64,
+
+    /// Average requests per second allowed across both clients (unlimited if unset)
+    #[arg(long, value_parser = parse_rate, conflicts_with = "arrival_rate")]
+    pub rps: Option<f64>,
+
+    /// Requests that may be sent back to back before `--rps` spacing applies
+    #[arg(
+        long,
+        default_value_t = 1,
+        requires = "rps",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub burst: u32,
+
+    /// Benchmark against this many embedded mock rpc servers on loopback instead of `--url`
+    #[arg(
+        long,
+        value_name = "COUNT",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        conflicts_with_all = ["urls", "replay"],
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    pub mock: Option<u16>,
+
+    /// Write a json report to this path (`-` for stdout, which replaces the results table)
+    #[arg(long, value_name = "PATH")]
+    pub json: Option<PathBuf>,
+
+    /// Write a Chrome trace of every request's phases to this path, to open in Perfetto
+    ///
+    /// Only the raw and batch modes break requests down into serialize, send,
+    /// await and deserialize. The sync and async modes show `build` and a
+    /// single `rpc_client` span, since the stock client's http sender isn't
+    /// instrumented.
+    #[arg(long, value_name = "PATH")]
+    pub trace: Option<PathBuf>,
+
+    /// Outcome every request is expected to have; others are flagged in the results
+    #[arg(long, value_enum, default_value_t = Expect::Auto)]
+    pub expect: Expect,
+
+    /// JSON-RPC error code expected with `--expect rpc-error`
+    #[arg(long, allow_negative_numbers = true)]
+    pub expect_code: Option<i64>,
+
+    /// Seconds before a request times out
+    #[arg(
+        long,
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub timeout: u64,
+
+    #[command(flatten)]
+    pub workload: WorkloadArgs,
+
+    #[command(flatten)]
+    pub method: MethodArgs,
+
+    #[command(flatten)]
+    pub simulate: SimulateArgs,
+
+    #[command(flatten)]
+    pub load: LoadArgs,
+
+    #[command(flatten)]
+    pub concurrency: ConcurrencyArgs,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    #[command(flatten)]
+    pub thresholds: ThresholdArgs,
+
+    #[command(flatten)]
+    pub faults: FaultArgs,
+
+    #[command(flatten)]
+    pub record: RecordArgs,
+
+    #[command(flatten)]
+    pub baseline: BaselineArgs,
+}
+
+impl Default for BenchArgs {
+    /// `bench` arguments as if no flags were passed
+    fn default() -> BenchArgs {
+        let command = BenchArgs::augment_args(clap::Command::new("bench"));
+        BenchArgs::from_arg_matches(&command.get_matches_from(["bench"]))
+            .expect("default arguments are valid")
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// TOML file with the endpoints, modes and other `bench` options to run
+    #[arg(value_name = "PATH")]
+    pub scenario: PathBuf,
+
+    /// Write a json report to this path (`-` for stdout, which replaces the results table)
+    #[arg(long, value_name = "PATH")]
+    pub json: Option<PathBuf>,
+
+    /// Write a Chrome trace of every request's phases to this path, to open in Perfetto
+    ///
+    /// Only the raw and batch modes break requests down into serialize, send,
+    /// await and deserialize. The sync and async modes show `build` and a
+    /// single `rpc_client` span, since the stock client's http sender isn't
+    /// instrumented.
+    #[arg(long, value_name = "PATH")]
+    pub trace: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub record: RecordArgs,
+
+    #[command(flatten)]
+    pub baseline: BaselineArgs,
+}
+
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct WorkloadArgs {
+    /// Transactions to simulate
+    #[arg(short, long, value_enum, default_value_t = WorkloadKind::Empty)]
+    pub workload: WorkloadKind,
+
+    /// Keypair file paying for signed workloads (a fresh, unfunded keypair if unset)
+    #[arg(long, value_name = "PATH")]
+    pub payer: Option<PathBuf>,
+
+    /// Transfers per transaction in the multi workload
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    pub instructions: u64,
+}
+
+/// The rpc call being benchmarked
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct MethodArgs {
+    /// Rpc method to benchmark
+    #[arg(long, value_enum, default_value_t = Method::SimulateTransaction)]
+    pub method: Method,
+
+    /// Comma separated accounts queried by getAccountInfo, getBalance (first
+    /// only) and getMultipleAccounts
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "PUBKEY",
+        default_value = "11111111111111111111111111111111"
+    )]
+    #[serde(serialize_with = "serialize_pubkeys")]
+    pub addresses: Vec<Pubkey>,
+
+    /// Bank commitment to query (the client defaults to finalized)
+    #[arg(long, value_enum)]
+    pub commitment: Option<Commitment>,
+}
+
+impl MethodArgs {
+    pub fn commitment_config(&self) -> Option<CommitmentConfig> {
+        self.commitment.map(|commitment| CommitmentConfig {
+            commitment: match commitment {
+                Commitment::Processed => CommitmentLevel::Processed,
+                Commitment::Confirmed => CommitmentLevel::Confirmed,
+                Commitment::Finalized => CommitmentLevel::Finalized,
+            },
+        })
+    }
+}
+
+/// Options passed to `simulateTransaction`
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct SimulateArgs {
+    /// Have the node verify transaction signatures
+    #[arg(long)]
+    pub sig_verify: bool,
+
+    /// Have the node swap in its latest blockhash before simulating
+    #[arg(long, conflicts_with = "sig_verify")]
+    pub replace_recent_blockhash: bool,
+
+    /// Transaction encoding sent to the node (the client picks base64 by default)
+    #[arg(long, value_enum)]
+    pub encoding: Option<TxEncoding>,
+
+    /// Comma separated accounts whose post-simulation state is returned
+    #[arg(long, value_delimiter = ',', value_name = "PUBKEY")]
+    #[serde(serialize_with = "serialize_pubkeys")]
+    pub accounts: Vec<Pubkey>,
+
+    /// Encoding of the returned accounts
+    #[arg(long, value_enum, requires = "accounts")]
+    pub accounts_encoding: Option<AccountEncoding>,
+
+    /// Minimum slot the node must have reached to simulate
+    #[arg(long)]
+    pub min_context_slot: Option<u64>,
+}
+
+impl SimulateArgs {
+    // Newer solana versions add fields, left at their defaults
+    #[allow(clippy::needless_update)]
+    pub fn config(&self, commitment: Option<CommitmentConfig>) -> RpcSimulateTransactionConfig {
+        RpcSimulateTransactionConfig {
+            sig_verify: self.sig_verify,
+            replace_recent_blockhash: self.replace_recent_blockhash,
+            commitment,
+            encoding: self.encoding.map(|encoding| match encoding {
+                TxEncoding::Base58 => UiTransactionEncoding::Base58,
+                TxEncoding::Base64 => UiTransactionEncoding::Base64,
+            }),
+            accounts: (!self.accounts.is_empty()).then(|| RpcSimulateTransactionAccountsConfig {
+                encoding: self.accounts_encoding.map(|encoding| match encoding {
+                    AccountEncoding::Base64 => UiAccountEncoding::Base64,
+                    AccountEncoding::Base64Zstd => UiAccountEncoding::Base64Zstd,
+                    AccountEncoding::JsonParsed => UiAccountEncoding::JsonParsed,
+                }),
+                addresses: self.accounts.iter().map(Pubkey::to_string).collect(),
+            }),
+            min_context_slot: self.min_context_slot,
+            ..RpcSimulateTransactionConfig::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxEncoding {
+    Base58,
+    Base64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccountEncoding {
+    Base64,
+    #[value(name = "base64+zstd")]
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    #[value(name = "jsonParsed")]
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
+
+/// Open-loop load generation, replacing the closed-loop default
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct LoadArgs {
+    /// Send requests open-loop at this many per second, measuring latency
+    /// from each request's intended send time
+    #[arg(long, value_parser = parse_rate)]
+    pub arrival_rate: Option<f64>,
+
+    /// Seconds to sustain `--arrival-rate` for, replacing `--requests`
+    #[arg(
+        long,
+        value_parser = parse_seconds,
+        requires = "arrival_rate",
+        conflicts_with = "requests"
+    )]
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct ConcurrencyArgs {
+    /// Threads in the rayon pool driving the sync client
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub rayon_threads: usize,
+
+    /// Tokio runtime flavor driving the async client
+    #[arg(long, value_enum, default_value_t = RuntimeFlavor::MultiThread)]
+    pub runtime: RuntimeFlavor,
+
+    /// Worker threads of the multi-thread tokio runtime
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub worker_threads: usize,
+
+    /// Maximum concurrent requests issued by the async client (unbounded if unset)
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_in_flight: Option<usize>,
+}
+
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct RetryArgs {
+    /// Times to retry a request that failed with a transient error
+    #[arg(long, default_value_t = 0)]
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubling on each one after
+    #[arg(long, default_value_t = 100)]
+    pub backoff_ms: u64,
+
+    /// Upper bound on the delay between retries
+    #[arg(long, default_value_t = 5_000)]
+    pub max_backoff_ms: u64,
+
+    /// Transient error classes to retry
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Transient::RateLimited, Transient::Transport, Transient::Unhealthy]
+    )]
+    pub retry_on: Vec<Transient>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuntimeFlavor {
+    CurrentThread,
+    MultiThread,
+}
+
+/// Adverse conditions injected by the mock rpc server (`serve`, or `bench
+/// --mock`). Fault probabilities are per http request and may add up to 1.
+#[derive(Debug, Clone, Default, Args, Serialize)]
+pub struct FaultArgs {
+    /// Delay before each response in milliseconds: fixed:MS, uniform:MIN,MAX,
+    /// normal:MEAN,SD, exponential:MEAN or lognormal:MEDIAN,SIGMA
+    #[arg(long, value_name = "DIST")]
+    #[serde(serialize_with = "serialize_optional_display")]
+    pub fault_latency: Option<Latency>,
+
+    /// Probability of answering every call with invalid params (-32602)
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_invalid_params: f64,
+
+    /// Probability of answering every call with node unhealthy (-32005)
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_unhealthy: f64,
+
+    /// Probability of answering with HTTP 429 Too Many Requests
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_rate_limited: f64,
+
+    /// Probability of answering with HTTP 503 Service Unavailable
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_unavailable: f64,
+
+    /// Probability of answering with a body that isn't valid JSON
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_malformed: f64,
+
+    /// Probability of closing the connection halfway through the body
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_truncated: f64,
+
+    /// Probability of closing the connection without answering
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_fraction)]
+    pub fault_drop: f64,
+}
+
+/// Limits every mode must stay within, or the run exits with status 1
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct ThresholdArgs {
+    /// Highest median latency allowed, in micros
+    #[arg(long, value_name = "MICROS")]
+    pub max_p50_us: Option<u64>,
+
+    /// Highest 90th percentile latency allowed, in micros
+    #[arg(long, value_name = "MICROS")]
+    pub max_p90_us: Option<u64>,
+
+    /// Highest 99th percentile latency allowed, in micros
+    #[arg(long, value_name = "MICROS")]
+    pub max_p99_us: Option<u64>,
+
+    /// Highest fraction of requests the node may not answer with a result
+    #[arg(long, value_name = "FRACTION", value_parser = parse_fraction)]
+    pub max_error_rate: Option<f64>,
+
+    /// Lowest requests per second allowed
+    #[arg(long, value_parser = parse_rate)]
+    pub min_rps: Option<f64>,
+
+    /// Most requests allowed an outcome other than `--expect`
+    #[arg(long)]
+    pub max_unexpected: Option<u64>,
+}
+
+/// Capturing a run's JSON-RPC traffic, and running offline against a capture
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct RecordArgs {
+    /// Record every JSON-RPC call to the endpoints and its response to this
+    /// file, as JSON lines
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Benchmark against an embedded server answering with the responses
+    /// recorded in this file instead of `--url`
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    /// Delay replayed responses by the latency they were recorded with
+    #[arg(long, requires = "replay")]
+    pub replay_latency: bool,
+}
+
+/// Saving runs and checking later runs against them for regressions
+#[derive(Debug, Clone, Args, Serialize)]
+pub struct BaselineArgs {
+    /// Save this run's latency samples as a baseline with this name
+    #[arg(long, value_name = "NAME", value_parser = parse_baseline_name)]
+    pub save_baseline: Option<String>,
+
+    /// Compare this run against the saved baseline with this name
+    #[arg(long, value_name = "NAME", value_parser = parse_baseline_name)]
+    pub baseline: Option<String>,
+
+    /// Directory baselines are saved in
+    #[arg(long, value_name = "PATH", default_value = ".tx_sim/baselines")]
+    pub baseline_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Address to bind the mock rpc server to
+    #[arg(short, long, default_value = "127.0.0.1:8899")]
+    pub bind: SocketAddr,
+
+    /// Address to bind the mock pubsub server to
+    #[arg(long, default_value = "127.0.0.1:8900")]
+    pub pubsub_bind: SocketAddr,
+
+    /// Milliseconds between the mock pubsub server's notifications
+    #[arg(long, default_value_t = 400, value_parser = clap::value_parser!(u64).range(1..))]
+    pub notify_interval_ms: u64,
+
+    #[command(flatten)]
+    pub faults: FaultArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Recording written by `bench --record`
+    #[arg(value_name = "PATH")]
+    pub recording: PathBuf,
+
+    /// Address to bind the replay server to
+    #[arg(short, long, default_value = "127.0.0.1:8899")]
+    pub bind: SocketAddr,
+
+    /// Delay responses by the latency they were recorded with
+    #[arg(long)]
+    pub latency: bool,
+}
+
+#[derive(Debug, Args, Serialize)]
+pub struct PubsubArgs {
+    /// Websocket url, or an rpc url or cluster moniker mapped to its websocket
+    /// url the way the solana cli does
+    #[arg(short = 'u', long, default_value = "mainnet", value_parser = parse_ws_endpoint)]
+    pub url: String,
+
+    /// Subscription to benchmark
+    #[arg(short, long, value_enum, default_value_t = Subscription::Slot)]
+    pub subscription: Subscription,
+
+    /// Concurrent subscriptions, all sharing one connection
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub subscriptions: u32,
+
+    /// Seconds each subscription listens for notifications
+    #[arg(short, long, default_value_t = 10.0, value_parser = parse_seconds)]
+    pub duration: f64,
+
+    /// Account watched by account subscriptions (the clock sysvar changes every slot)
+    #[arg(long, default_value = "SysvarC1ock11111111111111111111111111111111")]
+    #[serde(serialize_with = "serialize_display")]
+    pub account: Pubkey,
+
+    /// Signature watched by signature subscriptions (a random one, which never
+    /// lands on a real cluster, if unset)
+    #[arg(long)]
+    #[serde(serialize_with = "serialize_optional_display")]
+    pub signature: Option<Signature>,
+
+    /// Benchmark against an embedded mock pubsub server on loopback instead of `--url`
+    #[arg(long, conflicts_with = "url")]
+    pub mock: bool,
+
+    /// Milliseconds between the embedded mock's notifications
+    #[arg(long, default_value_t = 400, value_parser = clap::value_parser!(u64).range(1..))]
+    pub notify_interval_ms: u64,
+
+    /// Write a json report to this path (`-` for stdout, which replaces the results table)
+    #[arg(long, value_name = "PATH")]
+    #[serde(skip)]
+    pub json: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Blocking `RpcClient` driven by rayon
+    Sync,
+    /// Nonblocking `RpcClient` driven by tokio
+    Async,
+    /// JSON-RPC posted directly with reqwest, driven by tokio
+    Raw,
+    /// Raw JSON-RPC packed `--batch-size` calls per request, driven by tokio
+    Batch,
+    /// `simulateTransaction` in an in-process bank through `BanksClient`, with
+    /// no network, driven by tokio (needs the `banks` feature)
+    Banks,
+}
+
+impl Mode {
+    /// Row label in the results tables
+    pub fn label(self) -> &'static str {
+        match self {
+            Mode::Sync => "synchronous",
+            Mode::Async => "asynchronous",
+            Mode::Raw => "raw http",
+            Mode::Batch => "batch",
+            Mode::Banks => "in-process bank",
+        }
+    }
+}
+
+/// Expands a cluster moniker into its endpoint url, passing urls through untouched
+pub(crate) fn parse_endpoint(s: &str) -> Result<String, String> {
+    let url = match s {
+        "m" | "mainnet" | "mainnet-beta" => MAINNET_BETA_ENDPOINT,
+        "d" | "devnet" => DEVNET_ENDPOINT,
+        "t" | "testnet" => TESTNET_ENDPOINT,
+        "l" | "localhost" => LOCALHOST_ENDPOINT,
+        url if url.starts_with("http://") || url.starts_with("https://") => url,
+        other => {
+            return Err(format!(
+                "expected a url or one of mainnet, devnet, testnet, localhost; got {other:?}"
+            ))
+        }
+    };
+    Ok(url.to_string())
+}
+
+/// Expands a cluster moniker or rpc url into its websocket url, which by
+/// convention listens on the rpc port plus one
+fn parse_ws_endpoint(s: &str) -> Result<String, String> {
+    if s.starts_with("ws://") || s.starts_with("wss://") {
+        return Ok(s.to_string());
+    }
+    let url = parse_endpoint(s)?;
+    let (scheme, rest) = url.split_once("://").expect("endpoints have a scheme");
+    let scheme = if scheme == "https" { "wss" } else { "ws" };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let port = authority
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?.checked_add(1)?)));
+    Ok(match port {
+        Some((host, port)) => format!("{scheme}://{host}:{port}{path}"),
+        None => format!("{scheme}://{authority}{path}"),
+    })
+}
+
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= MIN_RATE => Ok(rate),
+        _ => Err(format!(
+            "expected at least {MIN_RATE} requests per second; got {s:?}"
+        )),
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Ok(seconds),
+        _ => Err(format!("expected a positive number of seconds; got {s:?}")),
+    }
+}
+
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("expected a fraction between 0 and 1; got {s:?}")),
+    }
+}
+
+fn parse_baseline_name(s: &str) -> Result<String, String> {
+    baseline::check_name(s)?;
+    Ok(s.to_string())
+}
+
+fn serialize_display<S: Serializer>(
+    value: &impl fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn serialize_optional_display<S: Serializer>(
+    value: &Option<impl fmt::Display>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Writes pubkeys in base58 rather than as byte arrays
+fn serialize_pubkeys<S: Serializer>(pubkeys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(pubkeys.iter().map(Pubkey::to_string))
+}
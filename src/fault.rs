@@ -0,0 +1,177 @@
+use std::{f64::consts::PI, fmt, str::FromStr, time::Duration};
+
+use rand::Rng;
+
+use crate::cli::FaultArgs;
+
+/// Longest delay the mock adds before answering. Longer samples, like the
+/// tail of a wide distribution, are cut down to it.
+pub const MAX_DELAY: Duration = Duration::from_secs(3_600);
+
+/// Distribution of the delay the mock adds before answering, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Latency {
+    Fixed(f64),
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+    Exponential {
+        mean: f64,
+    },
+    /// Long-tailed, like real node latencies
+    LogNormal {
+        median: f64,
+        sigma: f64,
+    },
+}
+
+impl Latency {
+    /// Draws a delay, clamping samples to between zero and [`MAX_DELAY`]
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let millis = match *self {
+            Latency::Fixed(millis) => millis,
+            Latency::Uniform { min, max } => min + (max - min) * rng.gen::<f64>(),
+            Latency::Normal { mean, std_dev } => mean + std_dev * standard_normal(rng),
+            // Inverse transform, with 1 - u in (0, 1] so the log stays finite
+            Latency::Exponential { mean } => -mean * (1.0 - rng.gen::<f64>()).ln(),
+            Latency::LogNormal { median, sigma } => median * (sigma * standard_normal(rng)).exp(),
+        };
+        Duration::try_from_secs_f64(millis.max(0.0) / 1_000.0)
+            .unwrap_or(MAX_DELAY)
+            .min(MAX_DELAY)
+    }
+}
+
+impl FromStr for Latency {
+    type Err = String;
+
+    /// Parses `fixed:MS`, `uniform:MIN,MAX`, `normal:MEAN,SD`,
+    /// `exponential:MEAN` or `lognormal:MEDIAN,SIGMA`, with every parameter
+    /// at most [`MAX_DELAY`] in milliseconds
+    fn from_str(s: &str) -> Result<Latency, String> {
+        let max = MAX_DELAY.as_millis() as f64;
+        let usage = || {
+            format!(
+                "expected fixed:MS, uniform:MIN,MAX, normal:MEAN,SD, exponential:MEAN or \
+                 lognormal:MEDIAN,SIGMA, with parameters up to {max}; got {s:?}"
+            )
+        };
+        let (name, params) = s.split_once(':').ok_or_else(usage)?;
+        let params = params
+            .split(',')
+            .map(|param| match param.trim().parse::<f64>() {
+                Ok(param) if (0.0..=max).contains(&param) => Ok(param),
+                _ => Err(usage()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(match (name, params.as_slice()) {
+            ("fixed", &[millis]) => Latency::Fixed(millis),
+            ("uniform", &[min, max]) if min <= max => Latency::Uniform { min, max },
+            ("normal", &[mean, std_dev]) => Latency::Normal { mean, std_dev },
+            ("exponential", &[mean]) => Latency::Exponential { mean },
+            ("lognormal", &[median, sigma]) => Latency::LogNormal { median, sigma },
+            _ => return Err(usage()),
+        })
+    }
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Latency::Fixed(millis) => write!(f, "fixed:{millis}"),
+            Latency::Uniform { min, max } => write!(f, "uniform:{min},{max}"),
+            Latency::Normal { mean, std_dev } => write!(f, "normal:{mean},{std_dev}"),
+            Latency::Exponential { mean } => write!(f, "exponential:{mean}"),
+            Latency::LogNormal { median, sigma } => write!(f, "lognormal:{median},{sigma}"),
+        }
+    }
+}
+
+/// A failure the mock can answer an http request with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// JSON-RPC error -32602 for every call in the request
+    InvalidParams,
+    /// JSON-RPC error -32005 for every call in the request
+    Unhealthy,
+    /// HTTP 429 Too Many Requests
+    RateLimited,
+    /// HTTP 503 Service Unavailable
+    Unavailable,
+    /// A 200 response whose body isn't valid JSON
+    Malformed,
+    /// A 200 response closed halfway through its body
+    Truncated,
+    /// The connection closed without any response
+    Drop,
+}
+
+/// Injected latency and faults, each fault with its probability per http
+/// request. At most one fault hits a request.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    latency: Option<Latency>,
+    faults: Vec<(Fault, f64)>,
+}
+
+impl Faults {
+    /// Fails if the probabilities add up to more than 1
+    pub fn new(args: &FaultArgs) -> Result<Faults, String> {
+        let faults = [
+            (Fault::InvalidParams, args.fault_invalid_params),
+            (Fault::Unhealthy, args.fault_unhealthy),
+            (Fault::RateLimited, args.fault_rate_limited),
+            (Fault::Unavailable, args.fault_unavailable),
+            (Fault::Malformed, args.fault_malformed),
+            (Fault::Truncated, args.fault_truncated),
+            (Fault::Drop, args.fault_drop),
+        ]
+        .into_iter()
+        .filter(|&(_, probability)| probability > 0.0)
+        .collect::<Vec<_>>();
+        let total = faults
+            .iter()
+            .map(|(_, probability)| probability)
+            .sum::<f64>();
+        // Leave room for rounding, so probabilities like 0.33, 0.56 and 0.11
+        // are accepted
+        if total > 1.0 + 1e-9 {
+            return Err(format!(
+                "fault probabilities add up to {total}, more than 1"
+            ));
+        }
+        Ok(Faults {
+            latency: args.fault_latency,
+            faults,
+        })
+    }
+
+    /// Draws the delay and fault, if any, for one http request
+    pub fn roll(&self, rng: &mut impl Rng) -> (Duration, Option<Fault>) {
+        let delay = self
+            .latency
+            .map_or(Duration::ZERO, |latency| latency.sample(rng));
+        if self.faults.is_empty() {
+            return (delay, None);
+        }
+        let mut draw = rng.gen::<f64>();
+        for &(fault, probability) in &self.faults {
+            if draw < probability {
+                return (delay, Some(fault));
+            }
+            draw -= probability;
+        }
+        (delay, None)
+    }
+}
+
+/// Box-Muller transform of two uniform samples
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
@@ -0,0 +1,44 @@
+//! Benchmarks solana rpc clients against one or more endpoints.
+//!
+//! ```no_run
+//! use tx_sim::{operation::Method, Benchmark, Mode};
+//!
+//! let runs = Benchmark::builder()
+//!     .endpoint("http://localhost:8899")
+//!     .mode(Mode::Async)
+//!     .method(Method::GetSlot)
+//!     .run()
+//!     .expect("failed to set up benchmark");
+//! for run in runs {
+//!     println!("{} {}: {:?}", run.endpoint, run.mode.label(), run.stats.summary());
+//! }
+//! ```
+
+#[cfg(feature = "banks")]
+pub mod banks;
+pub mod baseline;
+pub mod benchmark;
+pub mod cli;
+pub mod fault;
+pub mod mock_pubsub;
+pub mod mock_server;
+pub mod operation;
+pub mod outcome;
+pub mod pubsub;
+pub mod rate_limit;
+pub mod raw_client;
+pub mod recording;
+pub mod report;
+pub mod retry;
+pub mod rpc_error;
+mod runner;
+pub mod scenario;
+pub mod schedule;
+pub mod stats;
+pub mod taxonomy;
+pub mod threshold;
+pub mod trace;
+pub mod workload;
+
+pub use benchmark::{Benchmark, BenchmarkBuilder, ModeRun};
+pub use cli::Mode;
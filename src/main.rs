@@ -1,99 +1,489 @@
-use std::{
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::{path::Path, process::ExitCode, time::Duration};
 
-use indicatif::ProgressBar;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use hdrhistogram::Histogram;
 use num_format::{Locale, ToFormattedString};
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use solana_client::{
-    nonblocking::rpc_client::RpcClient as AsyncRpcClient,
-    rpc_client::{RpcClient, SerializableTransaction},
+use solana_sdk::signature::Signature;
+use tx_sim::{
+    baseline::{Baseline, Comparison},
+    cli::{BenchArgs, Cli, Command, FaultArgs, Mode, PubsubArgs, ReplayArgs, RunArgs, ServeArgs},
+    fault::Faults,
+    mock_pubsub::MockPubsubServer,
+    mock_server::MockServer,
+    pubsub::{self, PubsubOptions, PubsubStats},
+    recording::{Recording, ReplayServer},
+    report::{PubsubReport, Report},
+    scenario::Scenario,
+    stats::{IterationSummary, LatencySummary, RunStats, Spread},
+    threshold::{self, Violation},
+    trace::ChromeLayer,
+    BenchmarkBuilder, ModeRun,
 };
-use solana_sdk::{instruction::Instruction, message::Message, pubkey::Pubkey};
 
-/// Number of transactions to simulate
-const TX_SIMS: u64 = 16;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Bench(args) => bench(args),
+        Command::Run(args) => run(args),
+        Command::Pubsub(args) => pubsub(args),
+        Command::Serve(args) => {
+            serve(args);
+            ExitCode::SUCCESS
+        }
+        Command::Replay(args) => {
+            replay(args);
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn run(args: RunArgs) -> ExitCode {
+    let mut bench_args = match Scenario::load(&args.scenario) {
+        Ok(bench_args) => bench_args,
+        Err(err) => {
+            eprintln!("error: {}: {err}", args.scenario.display());
+            return ExitCode::from(2);
+        }
+    };
+    bench_args.json = args.json;
+    bench_args.trace = args.trace;
+    // A replay stands in for the scenario's endpoints or mocks
+    if args.record.replay.is_some() {
+        bench_args.mock = None;
+    }
+    bench_args.record = args.record;
+    bench_args.baseline = args.baseline;
+    bench(bench_args)
+}
+
+fn serve(args: ServeArgs) {
+    let server = MockServer::start_with_faults(args.bind, faults(&args.faults))
+        .expect("failed to start mock rpc server");
+    let pubsub = MockPubsubServer::start(
+        args.pubsub_bind,
+        Duration::from_millis(args.notify_interval_ms),
+    )
+    .expect("failed to start mock pubsub server");
+    println!("Mock rpc server listening on {}", server.url());
+    println!("Mock pubsub server listening on {}", pubsub.url());
+    wait_for_ctrl_c();
+}
 
-/// Public solana mainnet beta endpoint
-const MAINNET_BETA_ENDPOINT: &'static str = "https://api.mainnet-beta.solana.com";
+fn replay(args: ReplayArgs) {
+    let recording = Recording::load(&args.recording).expect("failed to load recording");
+    let calls = recording.exchanges().len();
+    let server = ReplayServer::start(args.bind, recording, args.latency)
+        .expect("failed to start replay server");
+    println!("Replaying {calls} recorded calls on {}", server.url());
+    wait_for_ctrl_c();
+}
 
-#[tokio::main(worker_threads = 1)]
-async fn main() {
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(1)
-        .build_global()
-        .unwrap();
+fn wait_for_ctrl_c() {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build tokio runtime")
+        .block_on(tokio::signal::ctrl_c())
+        .expect("failed to listen for ctrl-c");
+}
 
-    // Initialize clients
-    let sync_client = Arc::new(RpcClient::new(MAINNET_BETA_ENDPOINT));
-    let async_client = Arc::new(AsyncRpcClient::new(MAINNET_BETA_ENDPOINT.to_string()));
+fn pubsub(mut args: PubsubArgs) -> ExitCode {
+    // Keep the mock alive for the whole run
+    let _mock = args.mock.then(|| {
+        let server = MockPubsubServer::start(
+            ([127, 0, 0, 1], 0).into(),
+            Duration::from_millis(args.notify_interval_ms),
+        )
+        .expect("failed to start mock pubsub server");
+        args.url = server.url();
+        server
+    });
+    let options = PubsubOptions {
+        subscription: args.subscription,
+        subscriptions: args.subscriptions,
+        window: Duration::from_secs_f64(args.duration),
+        account: args.account,
+        signature: args.signature.unwrap_or_else(Signature::new_unique),
+    };
+    let stats = match tokio::runtime::Runtime::new()
+        .expect("failed to build tokio runtime")
+        .block_on(pubsub::run(&args.url, &options))
+    {
+        Ok(stats) => stats,
+        Err(err) => {
+            eprintln!("error: failed to connect to {}: {err}", args.url);
+            return ExitCode::from(2);
+        }
+    };
 
-    // Initialize progress bars
-    let sync_pb = ProgressBar::new(TX_SIMS);
-    let async_pb = ProgressBar::new(TX_SIMS);
+    if let Some(path) = &args.json {
+        PubsubReport::new(&args, &stats)
+            .write(path)
+            .expect("failed to write json report");
+        if path == Path::new("-") {
+            return ExitCode::SUCCESS;
+        }
+    }
 
-    // Expected error
-    // thread 'main' panicked at 'failed tx sim: ClientError { request: Some(SimulateTransaction),
+    println!();
+    println!(
+        "Results ({} {}, {} subscriptions for {}s, latencies in us)",
+        args.url, args.subscription, args.subscriptions, args.duration
+    );
+    println!(
+        "{:>20}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}",
+        "", "count", "min", "p50", "p90", "p99", "p99.9", "max"
+    );
+    print_summary("subscribe", &stats.subscribe);
+    print_summary("first notification", &stats.first_notification);
+    print_summary("interval", &stats.intervals);
+    println!();
+    println!(
+        "{} notifications, {:.1}/s per subscription, jitter {:.0} us",
+        stats.notifications,
+        stats.rate(),
+        stats.jitter
+    );
+    print_failures(&stats);
+    ExitCode::SUCCESS
+}
+
+fn print_summary(label: &str, histogram: &Histogram<u64>) {
+    let fmt = |micros: u64| micros.to_formatted_string(&Locale::en);
+    let summary = LatencySummary::from_histogram(histogram);
+    println!(
+        "{:>20}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}",
+        format!("{label}:"),
+        fmt(summary.count),
+        fmt(summary.min),
+        fmt(summary.p50),
+        fmt(summary.p90),
+        fmt(summary.p99),
+        fmt(summary.p999),
+        fmt(summary.max),
+    );
+}
+
+fn print_failures(stats: &PubsubStats) {
+    if stats.failures.is_empty() {
+        return;
+    }
+    println!();
+    println!("Failed subscriptions");
+    for (error, count) in &stats.failures {
+        println!("{:>20}  {:>8}  {error}", "", count);
+    }
+}
+
+/// Exits with a usage error if the fault probabilities don't add up
+fn faults(args: &FaultArgs) -> Faults {
+    Faults::new(args)
+        .unwrap_or_else(|err| Cli::command().error(ErrorKind::ValueValidation, err).exit())
+}
+
+/// Runs the benchmark, failing if any mode broke a threshold
+fn bench(mut args: BenchArgs) -> ExitCode {
+    // Expected error for the empty workload
+    // ClientError { request: Some(SimulateTransaction),
     // kind: RpcError(RpcResponseError
-    //    { code: -32602, message: "invalid transaction: Transaction failed to sanitize accounts offsets correctly", data: Empty }) }',
-    //     src/main.rs:29:14
-    // note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
-
-    // Time synchronous simulations
-    let sync_timer = Instant::now();
-    (0..TX_SIMS)
-        .into_par_iter()
-        .for_each_with(sync_client, |client, _| {
-            client
-                .simulate_transaction(&transaction_builder())
-                .expect_err("tx sim should fail");
-            sync_pb.inc(1);
-        });
-    let sync_time = sync_timer.elapsed().as_micros();
-    sync_pb.finish();
-
-    println!("Sleeping to wait for mb rpc rate limits");
-    std::thread::sleep(Duration::from_secs(20));
-
-    // Time asynchronous simulations
-    let async_timer = Instant::now();
-    tokio_scoped::scope(|scope| {
-        for _ in 0..TX_SIMS {
-            let arc_client = Arc::clone(&async_client);
-            let pb = async_pb.clone();
-            scope.spawn(async move {
-                arc_client
-                    .simulate_transaction(&transaction_builder())
-                    .await
-                    .expect_err("tx sim should fail");
-                pb.inc(1);
-            });
+    //    { code: -32602, message: "invalid transaction: Transaction failed to sanitize accounts offsets correctly", data: Empty }) }
+
+    let trace = args
+        .trace
+        .as_ref()
+        .map(|_| ChromeLayer::install().expect("failed to install tracing subscriber"));
+    // Loaded up front so a missing baseline doesn't waste a run
+    let saved = match &args.baseline.baseline {
+        Some(name) => match Baseline::load(&args.baseline.baseline_dir, name) {
+            Ok(saved) => Some(saved),
+            Err(err) => {
+                eprintln!("error: failed to load baseline {name:?}: {err}");
+                return ExitCode::from(2);
+            }
+        },
+        None => None,
+    };
+    let benchmark = match BenchmarkBuilder::from_args(&args).and_then(BenchmarkBuilder::build) {
+        Ok(benchmark) => benchmark,
+        Err(err) => {
+            eprintln!("error: failed to set up benchmark: {err}");
+            return ExitCode::from(2);
         }
-    });
-    let async_time = async_timer.elapsed().as_micros();
-    async_pb.finish();
+    };
+    // Reported as benchmarked, mocks and all
+    args.urls = benchmark.endpoints().to_vec();
+    args.requests = benchmark.requests();
+    let expectation = benchmark.expectation();
+    let runs = benchmark.run();
+    if let (Some(trace), Some(path)) = (&trace, &args.trace) {
+        trace.write(path).expect("failed to write trace");
+        eprintln!("Wrote {} spans to {}", trace.spans(), path.display());
+    }
+
+    let mut report = Report::new(&args, &expectation);
+    let mut baseline = Baseline::new(args.baseline.save_baseline.as_deref().unwrap_or_default());
+    for run in &runs {
+        report.push(&run.endpoint, run.mode, &run.stats, &run.iterations);
+        baseline.push(&run.endpoint, run.mode, &run.stats, &run.iterations);
+    }
+    if let Some(saved) = &saved {
+        report.comparison = saved.compare(&baseline);
+    }
+    if args.baseline.save_baseline.is_some() {
+        let path = baseline
+            .save(&args.baseline.baseline_dir)
+            .expect("failed to save baseline");
+        eprintln!("Saved baseline to {}", path.display());
+    }
+    report.violations = threshold::check(&args.thresholds, &runs);
+    let status = if report.violations.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    };
+    if let Some(path) = &args.json {
+        report.write(path).expect("failed to write json report");
+        if path == Path::new("-") {
+            return status;
+        }
+    }
+
+    println!();
+    println!(
+        "Results ({}, {} requests x {} iterations, latencies in us)",
+        args.method.method, args.requests, args.iterations
+    );
+    println!(
+        "{:>20}  {:>10}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>9}  {:>6}  {:>6}  {:>7}",
+        "",
+        "total",
+        "min",
+        "p50",
+        "p90",
+        "p99",
+        "p99.9",
+        "max",
+        "req/s",
+        "errors",
+        "err %",
+        "retries"
+    );
+    for_each_endpoint(&runs, print_stats);
+    print_iterations(&runs);
+    print_overhead(
+        &runs,
+        "Client overhead over raw http",
+        Mode::Raw,
+        &[Mode::Sync, Mode::Async],
+    );
+    print_overhead(
+        &runs,
+        "Network and rpc overhead over the in-process bank",
+        Mode::Banks,
+        &[Mode::Sync, Mode::Async, Mode::Raw, Mode::Batch],
+    );
+
+    println!();
+    println!("Outcomes (expected {expectation})");
+    for_each_endpoint(&runs, print_outcomes);
+
+    if runs.iter().any(|run| !run.stats.error_breakdown.is_empty()) {
+        println!();
+        println!("Errors");
+        for_each_endpoint(&runs, print_errors);
+    }
+
+    if let Some(name) = &args.baseline.baseline {
+        print_comparison(name, &report.comparison);
+    }
+    print_violations(&report.violations);
+    status
+}
+
+fn print_violations(violations: &[Violation]) {
+    if violations.is_empty() {
+        return;
+    }
+    println!();
+    println!("Thresholds broken");
+    let mut endpoint = None;
+    for violation in violations {
+        if endpoint != Some(&violation.endpoint) {
+            endpoint = Some(&violation.endpoint);
+            println!("{}", violation.endpoint);
+        }
+        println!(
+            "{:>20}  {violation}",
+            format!("{}:", violation.mode.label())
+        );
+    }
+}
+
+fn print_comparison(name: &str, comparison: &[Comparison]) {
+    println!();
+    println!("Compared with baseline {name:?} (medians, Mann-Whitney U test)");
+    if comparison.is_empty() {
+        println!("{:>20}  no modes in common", "");
+        return;
+    }
+    let mut endpoint = None;
+    for mode in comparison {
+        if endpoint != Some(&mode.endpoint) {
+            endpoint = Some(&mode.endpoint);
+            println!("{}", mode.endpoint);
+        }
+        println!("{:>20}", format!("{}:", mode.mode.label()));
+        for metric in &mode.metrics {
+            let p_value = metric
+                .p_value
+                .map(|p| format!("p={p:.3}"))
+                .unwrap_or_default();
+            println!(
+                "{:>20}  {:>10.1} -> {:<10.1}  {:>+7.1}%  {:>7}  {}",
+                format!("{}:", metric.metric),
+                metric.baseline,
+                metric.current,
+                metric.change * 100.0,
+                p_value,
+                metric.verdict,
+            );
+        }
+    }
+}
+
+/// Prints each endpoint followed by a row per mode run against it
+fn for_each_endpoint(runs: &[ModeRun], print: fn(&str, &RunStats)) {
+    let mut endpoint = None;
+    for run in runs {
+        if endpoint != Some(&run.endpoint) {
+            endpoint = Some(&run.endpoint);
+            println!("{}", run.endpoint);
+        }
+        print(run.mode.label(), &run.stats);
+    }
+}
+
+/// Prints the mean, standard deviation and confidence interval of each
+/// mode's headline numbers, if modes were run more than once
+fn print_iterations(runs: &[ModeRun]) {
+    let summaries = runs
+        .iter()
+        .filter_map(|run| Some((run, IterationSummary::of(&run.iterations)?)))
+        .collect::<Vec<_>>();
+    let Some((first, _)) = summaries.first() else {
+        return;
+    };
 
     println!();
-    println!("Results");
     println!(
-        "    synchronous sims: {}",
-        sync_time.to_formatted_string(&Locale::en)
+        "Iterations ({} per mode, mean ± std dev [95% confidence interval], latencies in us)",
+        first.iterations.len()
     );
+    let mut endpoint = None;
+    for (run, summary) in summaries {
+        if endpoint != Some(&run.endpoint) {
+            endpoint = Some(&run.endpoint);
+            println!("{}", run.endpoint);
+        }
+        println!("{:>20}", format!("{}:", run.mode.label()));
+        print_spread("total", &summary.total_micros);
+        print_spread("p50", &summary.p50_micros);
+        print_spread("p90", &summary.p90_micros);
+        print_spread("p99", &summary.p99_micros);
+        print_spread("req/s", &summary.throughput);
+    }
+}
+
+fn print_spread(label: &str, spread: &Spread) {
     println!(
-        "   asynchronous sims: {}",
-        async_time.to_formatted_string(&Locale::en)
+        "{:>20}  {:>12.1} ± {:<10.1} [{:.1}, {:.1}]",
+        format!("{label}:"),
+        spread.mean,
+        spread.std_dev,
+        spread.ci_low,
+        spread.ci_high,
     );
 }
 
-fn transaction_builder() -> impl SerializableTransaction {
-    // Build an empty tx
-    solana_sdk::transaction::Transaction::new_unsigned(Message::new(
-        &[Instruction::new_with_bytes(
-            Pubkey::new_unique(),
-            &[],
-            vec![],
-        )],
-        None,
-    ))
+/// Prints how much slower the `compared` modes were than the `baseline` mode
+/// on the same endpoint, if the baseline mode was benchmarked
+fn print_overhead(runs: &[ModeRun], title: &str, baseline: Mode, compared: &[Mode]) {
+    let baseline_run = |endpoint: &str| {
+        runs.iter()
+            .find(|run| run.endpoint == endpoint && run.mode == baseline)
+    };
+    let compared = runs
+        .iter()
+        .filter(|run| compared.contains(&run.mode))
+        .filter_map(|run| Some((run, baseline_run(&run.endpoint)?)))
+        .collect::<Vec<_>>();
+    if compared.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("{title} (latencies in us)");
+    println!("{:>20}  {:>8}  {:>10}", "", "p50", "mean");
+    let mut endpoint = None;
+    for (run, raw) in compared {
+        if endpoint != Some(&run.endpoint) {
+            endpoint = Some(&run.endpoint);
+            println!("{}", run.endpoint);
+        }
+        let (summary, baseline) = (run.stats.summary(), raw.stats.summary());
+        println!(
+            "{:>20}  {:>+8}  {:>+10.1}",
+            format!("{}:", run.mode.label()),
+            summary.p50 as i64 - baseline.p50 as i64,
+            summary.mean - baseline.mean,
+        );
+    }
+}
+
+fn print_errors(label: &str, stats: &RunStats) {
+    if stats.error_breakdown.is_empty() {
+        return;
+    }
+    println!("{:>20}", format!("{label}:"));
+    for (error, count) in &stats.error_breakdown {
+        let code = error.code.map(|code| code.to_string()).unwrap_or_default();
+        println!(
+            "{:>20}  {:>8}  {:<12}  {:>6}  {}",
+            "", count, error.kind, code, error.message
+        );
+    }
+}
+
+fn print_outcomes(label: &str, stats: &RunStats) {
+    let outcomes = stats
+        .outcomes
+        .iter()
+        .map(|(outcome, count)| format!("{outcome}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("{:>20}  {outcomes}", format!("{label}:"));
+    if stats.unexpected > 0 {
+        println!("{:>20}  {} unexpected", "", stats.unexpected);
+    }
+}
+
+fn print_stats(label: &str, stats: &RunStats) {
+    let fmt = |micros: u64| micros.to_formatted_string(&Locale::en);
+    let summary = stats.summary();
+    println!(
+        "{:>20}  {:>10}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>9.1}  {:>6}  {:>6.1}  {:>7}",
+        format!("{label}:"),
+        stats.elapsed.as_micros().to_formatted_string(&Locale::en),
+        fmt(summary.min),
+        fmt(summary.p50),
+        fmt(summary.p90),
+        fmt(summary.p99),
+        fmt(summary.p999),
+        fmt(summary.max),
+        stats.throughput(),
+        stats.errors,
+        stats.error_rate() * 100.0,
+        stats.retries,
+    );
 }
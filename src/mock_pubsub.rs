@@ -0,0 +1,158 @@
+use std::{collections::BTreeMap, io, net::SocketAddr, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    mock_server::{account, error_response, ServerThread, BASE_SLOT, SOLANA_CORE_VERSION},
+    rpc_error::{INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR},
+};
+
+/// A local websocket server answering slot, account and signature
+/// subscriptions with synthetic notifications.
+///
+/// Every `interval` each open subscription is notified, as if a slot had
+/// passed. Signature subscriptions are notified once and then closed, like a
+/// validator does once the signature is processed. Runs on its own thread and
+/// shuts down when dropped, like [`MockServer`](crate::mock_server::MockServer).
+pub struct MockPubsubServer {
+    server: ServerThread,
+}
+
+impl MockPubsubServer {
+    /// Binds `addr` (use port 0 for an ephemeral port) and starts serving
+    pub fn start(addr: SocketAddr, interval: Duration) -> io::Result<MockPubsubServer> {
+        let server = ServerThread::serve("mock-pubsub", addr, move |listener| async move {
+            let listener = TcpListener::from_std(listener).expect("listener is bound");
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve_connection(stream, interval));
+                    }
+                    Err(e) => eprintln!("mock pubsub server error: {e}"),
+                }
+            }
+        })?;
+        Ok(MockPubsubServer { server })
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.server.addr())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Slot,
+    Account,
+    Signature,
+}
+
+impl Kind {
+    fn notification(self) -> &'static str {
+        match self {
+            Kind::Slot => "slotNotification",
+            Kind::Account => "accountNotification",
+            Kind::Signature => "signatureNotification",
+        }
+    }
+}
+
+/// Open subscriptions of one connection, keyed by subscription id
+#[derive(Default)]
+struct Subscriptions {
+    next_id: u64,
+    open: BTreeMap<u64, Kind>,
+}
+
+impl Subscriptions {
+    fn respond(&mut self, text: &str) -> Value {
+        let Ok(request) = serde_json::from_str::<Value>(text) else {
+            return error_response(Value::Null, PARSE_ERROR, "Parse error");
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return error_response(id, INVALID_PARAMS, "Invalid request");
+        };
+
+        let subscribe = |subscriptions: &mut Subscriptions, kind| {
+            subscriptions.next_id += 1;
+            subscriptions.open.insert(subscriptions.next_id, kind);
+            json!(subscriptions.next_id)
+        };
+        let result = match method {
+            "slotSubscribe" => subscribe(self, Kind::Slot),
+            "accountSubscribe" => subscribe(self, Kind::Account),
+            "signatureSubscribe" => subscribe(self, Kind::Signature),
+            "slotUnsubscribe" | "accountUnsubscribe" | "signatureUnsubscribe" => {
+                let id = request["params"][0].as_u64();
+                json!(id.and_then(|id| self.open.remove(&id)).is_some())
+            }
+            "getVersion" => json!({
+                "solana-core": SOLANA_CORE_VERSION,
+                "feature-set": 0,
+            }),
+            _ => return error_response(id, METHOD_NOT_FOUND, "Method not found"),
+        };
+        json!({ "jsonrpc": "2.0", "result": result, "id": id })
+    }
+
+    /// Notifications for every open subscription at `slot`, closing
+    /// signature subscriptions once notified
+    fn notify(&mut self, slot: u64) -> Vec<Value> {
+        let context = json!({ "slot": slot, "apiVersion": SOLANA_CORE_VERSION });
+        let notifications = self
+            .open
+            .iter()
+            .map(|(&subscription, &kind)| {
+                let result = match kind {
+                    Kind::Slot => json!({
+                        "slot": slot,
+                        "parent": slot - 1,
+                        "root": slot - 32,
+                    }),
+                    Kind::Account => json!({ "context": context, "value": account() }),
+                    Kind::Signature => json!({ "context": context, "value": { "err": null } }),
+                };
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": kind.notification(),
+                    "params": { "result": result, "subscription": subscription },
+                })
+            })
+            .collect();
+        self.open.retain(|_, kind| *kind != Kind::Signature);
+        notifications
+    }
+}
+
+async fn serve_connection(stream: TcpStream, interval: Duration) {
+    // Small frames would otherwise wait on the peer's delayed acks
+    stream.set_nodelay(true).ok();
+    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let mut subscriptions = Subscriptions::default();
+    let mut ticker = tokio::time::interval(interval);
+    let mut slot = BASE_SLOT;
+    loop {
+        let replies = tokio::select! {
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => vec![subscriptions.respond(&text)],
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            _ = ticker.tick() => {
+                slot += 1;
+                subscriptions.notify(slot)
+            }
+        };
+        for reply in replies {
+            if ws.send(Message::Text(reply.to_string())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+    thread::JoinHandle,
+    time::Instant,
+};
+
+use hyper::{
+    body::Bytes,
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use solana_sdk::{hash::Hash, sanitize::Sanitize, system_program, transaction::Transaction};
+use tokio::sync::oneshot;
+
+use crate::{
+    fault::{Fault, Faults},
+    rpc_error::{
+        INVALID_PARAMS, METHOD_NOT_FOUND, MIN_CONTEXT_SLOT_NOT_REACHED, NODE_UNHEALTHY,
+        PARSE_ERROR, SIGNATURE_VERIFICATION_FAILURE,
+    },
+};
+
+/// Slot reported by the mock when it starts
+pub const BASE_SLOT: u64 = 180_000_000;
+
+/// Target slot time used to advance the reported slot
+const SLOT_MS: u128 = 400;
+
+/// Compute units charged per simulated instruction
+const UNITS_PER_INSTRUCTION: u64 = 150;
+
+/// Lamports held by every account the mock reports
+const ACCOUNT_LAMPORTS: u64 = 1_000_000_000;
+
+/// Fee charged per signature
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Version reported to clients, which they use to pick encodings and commitments
+pub const SOLANA_CORE_VERSION: &str = "1.14.13";
+
+/// A local JSON-RPC server answering a subset of the solana rpc api.
+///
+/// The server runs on its own thread and runtime so it keeps serving while the
+/// benchmark blocks the caller's threads. It shuts down when dropped.
+///
+/// It can also inject latency and faults, to see how clients cope with them.
+pub struct MockServer {
+    server: ServerThread,
+}
+
+impl MockServer {
+    /// Binds `addr` (use port 0 for an ephemeral port) and starts serving
+    pub fn start(addr: SocketAddr) -> io::Result<MockServer> {
+        MockServer::start_with_faults(addr, Faults::default())
+    }
+
+    /// Like [`MockServer::start`], delaying and failing requests as `faults`
+    /// says
+    pub fn start_with_faults(addr: SocketAddr, faults: Faults) -> io::Result<MockServer> {
+        let state = Arc::new(MockState::new(faults));
+        let server =
+            ServerThread::spawn("mock-rpc", addr, move |req| handle(Arc::clone(&state), req))?;
+        Ok(MockServer { server })
+    }
+
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+}
+
+/// A server on its own thread and current thread runtime, stopped and joined
+/// when dropped
+pub(crate) struct ServerThread {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerThread {
+    /// Serves http, answering every request with `handler`. Failing a request
+    /// closes its connection.
+    pub(crate) fn spawn<H, F>(name: &str, addr: SocketAddr, handler: H) -> io::Result<ServerThread>
+    where
+        H: Fn(Request<Body>) -> F + Clone + Send + 'static,
+        F: Future<Output = io::Result<Response<Body>>> + Send + 'static,
+    {
+        let error_name = name.to_string();
+        ServerThread::serve(name, addr, move |listener| async move {
+            let make_service = make_service_fn(move |_| {
+                let handler = handler.clone();
+                async move { Ok::<_, Infallible>(service_fn(handler)) }
+            });
+            let server = Server::from_tcp(listener)
+                .expect("listener is bound")
+                .serve(make_service);
+            if let Err(e) = server.await {
+                eprintln!("{error_name} server error: {e}");
+            }
+        })
+    }
+
+    /// Binds `addr` and runs the future `serve` makes of the nonblocking
+    /// listener until it finishes or the server is dropped
+    pub(crate) fn serve<S, F>(name: &str, addr: SocketAddr, serve: S) -> io::Result<ServerThread>
+    where
+        S: FnOnce(TcpListener) -> F + Send + 'static,
+        F: Future<Output = ()>,
+    {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+
+        let thread = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    // Stop without waiting on open connections, whose clients
+                    // may live on a runtime that is no longer polled. Dropping
+                    // the runtime closes them.
+                    tokio::select! {
+                        _ = serve(listener) => {}
+                        _ = shutdown_rx => {}
+                    }
+                })
+            })?;
+
+        Ok(ServerThread {
+            addr,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub(crate) fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for ServerThread {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+struct MockState {
+    started: Instant,
+    blockhash: Hash,
+    faults: Faults,
+}
+
+impl MockState {
+    fn new(faults: Faults) -> MockState {
+        MockState {
+            started: Instant::now(),
+            blockhash: Hash::new_from_array([7; 32]),
+            faults,
+        }
+    }
+
+    fn slot(&self) -> u64 {
+        BASE_SLOT + (self.started.elapsed().as_millis() / SLOT_MS) as u64
+    }
+
+    /// Wraps `value` in the `{ context, value }` envelope used by most methods
+    fn with_context(&self, value: Value) -> Value {
+        json!({
+            "context": { "slot": self.slot(), "apiVersion": SOLANA_CORE_VERSION },
+            "value": value,
+        })
+    }
+
+    fn respond(&self, request: &Value, fault: Option<Fault>) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        match fault {
+            Some(Fault::InvalidParams) => {
+                return error_response(id, INVALID_PARAMS, "Invalid params: injected fault")
+            }
+            Some(Fault::Unhealthy) => {
+                return error_response(id, NODE_UNHEALTHY, "Node is unhealthy")
+            }
+            _ => {}
+        }
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return error_response(id, INVALID_PARAMS, "Invalid request");
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "getHealth" => Ok(json!("ok")),
+            "getVersion" => Ok(json!({
+                "solana-core": SOLANA_CORE_VERSION,
+                "feature-set": 0,
+            })),
+            "getGenesisHash" => Ok(json!(Hash::default().to_string())),
+            "getSlot" => Ok(json!(self.slot())),
+            "getBlockHeight" => Ok(json!(self.slot())),
+            "getEpochInfo" => {
+                let slot = self.slot();
+                Ok(json!({
+                    "absoluteSlot": slot,
+                    "blockHeight": slot,
+                    "epoch": slot / 432_000,
+                    "slotIndex": slot % 432_000,
+                    "slotsInEpoch": 432_000,
+                    "transactionCount": null,
+                }))
+            }
+            "getLatestBlockhash" => Ok(self.with_context(json!({
+                "blockhash": self.blockhash.to_string(),
+                "lastValidBlockHeight": self.slot() + 150,
+            }))),
+            "getBalance" => Ok(self.with_context(json!(ACCOUNT_LAMPORTS))),
+            "getAccountInfo" => Ok(self.with_context(account())),
+            "getMultipleAccounts" => {
+                let count = params.get(0).and_then(Value::as_array).map_or(0, Vec::len);
+                Ok(self.with_context(Value::Array(vec![account(); count])))
+            }
+            "getFeeForMessage" => Ok(self.with_context(json!(LAMPORTS_PER_SIGNATURE))),
+            "getMinimumBalanceForRentExemption" => Ok(json!(890_880)),
+            "simulateTransaction" => self.simulate_transaction(&params),
+            _ => Err((METHOD_NOT_FOUND, "Method not found".to_string())),
+        };
+
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err((code, message)) => error_response(id, code, &message),
+        }
+    }
+
+    /// Decodes and sanitizes the transaction the same way a validator would
+    /// before reporting a successful simulation
+    fn simulate_transaction(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let invalid = |message: String| (INVALID_PARAMS, message);
+
+        let encoded = params
+            .get(0)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("Invalid params: missing transaction".to_string()))?;
+        let config = params.get(1).cloned().unwrap_or(Value::Null);
+        let flag = |name: &str| config.get(name).and_then(Value::as_bool).unwrap_or(false);
+
+        let bytes = match config.get("encoding").and_then(Value::as_str) {
+            None | Some("base58") => bs58::decode(encoded)
+                .into_vec()
+                .map_err(|e| invalid(format!("invalid base58 encoding: {e:?}")))?,
+            Some("base64") => base64::decode(encoded)
+                .map_err(|e| invalid(format!("invalid base64 encoding: {e:?}")))?,
+            Some(other) => {
+                return Err(invalid(format!(
+                    "unsupported encoding: {other}. Supported encodings: base58, base64"
+                )))
+            }
+        };
+        let transaction: Transaction = bincode::deserialize(&bytes).map_err(|e| {
+            invalid(format!(
+                "failed to deserialize solana_sdk::transaction::Transaction: {e}"
+            ))
+        })?;
+
+        if let Some(min_context_slot) = config.get("minContextSlot").and_then(Value::as_u64) {
+            let slot = self.slot();
+            if slot < min_context_slot {
+                return Err((
+                    MIN_CONTEXT_SLOT_NOT_REACHED,
+                    format!("Minimum context slot has not been reached: {slot}"),
+                ));
+            }
+        }
+        if flag("sigVerify") && flag("replaceRecentBlockhash") {
+            return Err(invalid(
+                "sigVerify may not be used with replaceRecentBlockhash".to_string(),
+            ));
+        }
+        if transaction.sanitize().is_err() {
+            return Err(invalid(
+                "invalid transaction: Transaction failed to sanitize accounts offsets correctly"
+                    .to_string(),
+            ));
+        }
+        if flag("sigVerify") && transaction.verify().is_err() {
+            return Err((
+                SIGNATURE_VERIFICATION_FAILURE,
+                "Transaction signature verification failure".to_string(),
+            ));
+        }
+
+        let message = &transaction.message;
+        let mut logs = vec![];
+        for instruction in &message.instructions {
+            let program_id = message.account_keys[instruction.program_id_index as usize];
+            logs.push(format!("Program {program_id} invoke [1]"));
+            logs.push(format!(
+                "Program {program_id} consumed {UNITS_PER_INSTRUCTION} of 200000 compute units"
+            ));
+            logs.push(format!("Program {program_id} success"));
+        }
+        let accounts = config
+            .get("accounts")
+            .and_then(|accounts| accounts.get("addresses"))
+            .and_then(Value::as_array)
+            .map(|addresses| Value::Array(vec![account(); addresses.len()]));
+
+        Ok(self.with_context(json!({
+            "err": null,
+            "logs": logs,
+            "accounts": accounts,
+            "unitsConsumed": UNITS_PER_INSTRUCTION * message.instructions.len() as u64,
+            "returnData": null,
+        })))
+    }
+}
+
+/// A funded, empty system account
+pub fn account() -> Value {
+    json!({
+        "lamports": ACCOUNT_LAMPORTS,
+        "data": ["", "base64"],
+        "owner": system_program::id().to_string(),
+        "executable": false,
+        "rentEpoch": 0,
+        "space": 0,
+    })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": id,
+    })
+}
+
+/// Answers one http request, after any injected delay. Failing closes the
+/// connection without a response.
+async fn handle(state: Arc<MockState>, req: Request<Body>) -> io::Result<Response<Body>> {
+    let body = hyper::body::to_bytes(req.into_body()).await;
+    let (delay, fault) = state.faults.roll(&mut rand::thread_rng());
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+
+    let status = match fault {
+        Some(Fault::Drop) => {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "injected dropped connection",
+            ))
+        }
+        Some(Fault::RateLimited) => Some(StatusCode::TOO_MANY_REQUESTS),
+        Some(Fault::Unavailable) => Some(StatusCode::SERVICE_UNAVAILABLE),
+        _ => None,
+    };
+    if let Some(status) = status {
+        return Ok(Response::builder()
+            .status(status)
+            .body(Body::from(status.canonical_reason().unwrap_or_default()))
+            .expect("valid response"));
+    }
+
+    let response = match body {
+        Ok(body) => match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Array(requests)) if requests.is_empty() => {
+                error_response(Value::Null, INVALID_PARAMS, "Invalid request")
+            }
+            Ok(Value::Array(requests)) => {
+                requests.iter().map(|r| state.respond(r, fault)).collect()
+            }
+            Ok(request) => state.respond(&request, fault),
+            Err(_) => error_response(Value::Null, PARSE_ERROR, "Parse error"),
+        },
+        Err(_) => error_response(Value::Null, PARSE_ERROR, "Parse error"),
+    };
+    let response = response.to_string();
+
+    let body = match fault {
+        // What a node serializing a float badly might send
+        Some(Fault::Malformed) => Body::from(r#"{"jsonrpc":"2.0","result":NaN,"id":null}"#),
+        Some(Fault::Truncated) => {
+            // Promise the whole body, send half of it and hang up
+            let half = Bytes::copy_from_slice(&response.as_bytes()[..response.len() / 2]);
+            let (mut sender, body) = Body::channel();
+            tokio::spawn(async move {
+                sender.send_data(half).await.ok();
+                sender.abort();
+            });
+            return Ok(Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .header(CONTENT_LENGTH, response.len())
+                .body(body)
+                .expect("valid response"));
+        }
+        _ => Body::from(response),
+    };
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(body)
+        .expect("valid response"))
+}
@@ -0,0 +1,297 @@
+use std::{fmt, sync::Arc};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient as AsyncRpcClient,
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcSimulateTransactionConfig},
+    rpc_request::RpcRequest,
+    rpc_response::{Response, RpcSimulateTransactionResult},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    transaction::{Transaction, TransactionError},
+};
+use solana_transaction_status::UiTransactionEncoding;
+use tracing::{info_span, Instrument};
+
+#[cfg(feature = "banks")]
+use crate::banks::BankClient;
+use crate::{raw_client::RawClient, workload::Workload};
+
+/// Rpc methods that can be benchmarked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[value(rename_all = "camelCase")]
+pub enum Method {
+    SimulateTransaction,
+    GetLatestBlockhash,
+    GetAccountInfo,
+    GetMultipleAccounts,
+    GetBalance,
+    GetSlot,
+    GetFeeForMessage,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+impl Method {
+    /// Whether the method queries the operation's addresses
+    pub fn queries_accounts(self) -> bool {
+        matches!(
+            self,
+            Method::GetAccountInfo | Method::GetMultipleAccounts | Method::GetBalance
+        )
+    }
+
+    fn request(self) -> RpcRequest {
+        match self {
+            Method::SimulateTransaction => RpcRequest::SimulateTransaction,
+            Method::GetLatestBlockhash => RpcRequest::GetLatestBlockhash,
+            Method::GetAccountInfo => RpcRequest::GetAccountInfo,
+            Method::GetMultipleAccounts => RpcRequest::GetMultipleAccounts,
+            Method::GetBalance => RpcRequest::GetBalance,
+            Method::GetSlot => RpcRequest::GetSlot,
+            Method::GetFeeForMessage => RpcRequest::GetFeeForMessage,
+        }
+    }
+}
+
+/// Clients the async runner drives
+pub enum AsyncClient {
+    Rpc(AsyncRpcClient),
+    Raw(RawClient),
+    /// Only simulates transactions
+    #[cfg(feature = "banks")]
+    Bank(Box<BankClient>),
+}
+
+/// A benchmarked rpc call along with its parameters.
+///
+/// Calls are traced in phases: `build` for the transaction or message, then
+/// the raw client's `serialize`, `send`, `await` and `deserialize`, and
+/// `decode` for simulation results. `RpcClient` does all but `build` inside
+/// one `rpc_client` span, and the in-process bank inside a `simulate` span.
+pub struct Operation {
+    pub method: Method,
+    /// Accounts queried by the account and balance methods
+    pub addresses: Vec<Pubkey>,
+    pub commitment: CommitmentConfig,
+    pub simulate: RpcSimulateTransactionConfig,
+    /// Builds transactions to simulate and messages to price
+    pub workload: Arc<dyn Workload>,
+}
+
+/// Result of one call. Transaction errors are only reported by simulations
+/// the node ran but the transaction failed.
+pub type CallResult = ClientResult<Option<TransactionError>>;
+
+// `ClientError` is large, but boxing it would mean unboxing for every caller
+#[allow(clippy::result_large_err)]
+impl Operation {
+    /// Whether the rpc is expected to reject every call
+    pub fn rejected(&self) -> bool {
+        self.method == Method::SimulateTransaction && self.workload.rejected()
+    }
+
+    fn address(&self) -> &Pubkey {
+        self.addresses.first().expect("at least one address")
+    }
+
+    /// Builds the transaction to simulate or the message to price, if the
+    /// method takes one
+    fn transaction(&self) -> Option<Transaction> {
+        match self.method {
+            Method::SimulateTransaction | Method::GetFeeForMessage => {
+                Some(info_span!("build").in_scope(|| self.workload.build()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn call_blocking(&self, client: &RpcClient) -> CallResult {
+        let tx = self.transaction();
+        info_span!("rpc_client").in_scope(|| self.call_rpc_blocking(client, tx))
+    }
+
+    fn call_rpc_blocking(&self, client: &RpcClient, tx: Option<Transaction>) -> CallResult {
+        let tx = || tx.expect("method takes a transaction");
+        match self.method {
+            Method::SimulateTransaction => client
+                .simulate_transaction_with_config(&tx(), self.simulate.clone())
+                .map(|response| response.value.err),
+            Method::GetLatestBlockhash => client
+                .get_latest_blockhash_with_commitment(self.commitment)
+                .map(|_| None),
+            Method::GetAccountInfo => client
+                .get_account_with_commitment(self.address(), self.commitment)
+                .map(|_| None),
+            Method::GetMultipleAccounts => client
+                .get_multiple_accounts_with_commitment(&self.addresses, self.commitment)
+                .map(|_| None),
+            Method::GetBalance => client
+                .get_balance_with_commitment(self.address(), self.commitment)
+                .map(|_| None),
+            Method::GetSlot => client
+                .get_slot_with_commitment(self.commitment)
+                .map(|_| None),
+            Method::GetFeeForMessage => client.get_fee_for_message(&tx().message).map(|_| None),
+        }
+    }
+
+    pub async fn call(&self, client: &AsyncClient) -> CallResult {
+        match client {
+            AsyncClient::Rpc(client) => {
+                let tx = self.transaction();
+                self.call_rpc(client, tx)
+                    .instrument(info_span!("rpc_client"))
+                    .await
+            }
+            AsyncClient::Raw(client) => self.call_raw(client).await,
+            #[cfg(feature = "banks")]
+            AsyncClient::Bank(client) => {
+                let tx = info_span!("build").in_scope(|| self.workload.build());
+                client.simulate(tx).instrument(info_span!("simulate")).await
+            }
+        }
+    }
+
+    async fn call_rpc(&self, client: &AsyncRpcClient, tx: Option<Transaction>) -> CallResult {
+        let tx = || tx.expect("method takes a transaction");
+        match self.method {
+            Method::SimulateTransaction => client
+                .simulate_transaction_with_config(&tx(), self.simulate.clone())
+                .await
+                .map(|response| response.value.err),
+            Method::GetLatestBlockhash => client
+                .get_latest_blockhash_with_commitment(self.commitment)
+                .await
+                .map(|_| None),
+            Method::GetAccountInfo => client
+                .get_account_with_commitment(self.address(), self.commitment)
+                .await
+                .map(|_| None),
+            Method::GetMultipleAccounts => client
+                .get_multiple_accounts_with_commitment(&self.addresses, self.commitment)
+                .await
+                .map(|_| None),
+            Method::GetBalance => client
+                .get_balance_with_commitment(self.address(), self.commitment)
+                .await
+                .map(|_| None),
+            Method::GetSlot => client
+                .get_slot_with_commitment(self.commitment)
+                .await
+                .map(|_| None),
+            Method::GetFeeForMessage => client
+                .get_fee_for_message(&tx().message)
+                .await
+                .map(|_| None),
+        }
+    }
+
+    /// Sends the payload `RpcClient` would, decoding only what's needed to
+    /// tell a failed simulation apart
+    async fn call_raw(&self, client: &RawClient) -> CallResult {
+        let tx = self.transaction();
+        let result = client
+            .send(self.method.request(), || self.params(tx.as_ref()))
+            .await?;
+        info_span!("decode").in_scope(|| self.decode(result))
+    }
+
+    /// Sends `size` calls as one JSON-RPC batch, failing as a whole only if
+    /// the batch request does
+    pub async fn call_batch(
+        &self,
+        client: &RawClient,
+        size: usize,
+    ) -> ClientResult<Vec<CallResult>> {
+        let txs = (0..size).map(|_| self.transaction()).collect::<Vec<_>>();
+        let results = client
+            .send_batch(|| {
+                txs.iter()
+                    .map(|tx| Ok((self.method.request(), self.params(tx.as_ref())?)))
+                    .collect()
+            })
+            .await?;
+        info_span!("decode").in_scope(|| {
+            Ok(results
+                .into_iter()
+                .map(|result| result.and_then(|result| self.decode(result)))
+                .collect())
+        })
+    }
+
+    fn decode(&self, result: Value) -> CallResult {
+        match self.method {
+            Method::SimulateTransaction => {
+                let response: Response<RpcSimulateTransactionResult> =
+                    serde_json::from_value(result)?;
+                Ok(response.value.err)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Request params matching those `RpcClient` sends for this method, with
+    /// the transaction or message built for it
+    fn params(&self, tx: Option<&Transaction>) -> ClientResult<Value> {
+        let tx = || tx.expect("method takes a transaction");
+        let accounts = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            commitment: Some(self.commitment),
+            data_slice: None,
+            min_context_slot: None,
+        };
+        Ok(match self.method {
+            Method::SimulateTransaction => {
+                // The client defaults to base64 for nodes since 1.9
+                let encoding = self
+                    .simulate
+                    .encoding
+                    .unwrap_or(UiTransactionEncoding::Base64);
+                let config = RpcSimulateTransactionConfig {
+                    encoding: Some(encoding),
+                    commitment: Some(self.simulate.commitment.unwrap_or_default()),
+                    ..self.simulate.clone()
+                };
+                let tx = encode(tx(), encoding)?;
+                json!([tx, config])
+            }
+            Method::GetLatestBlockhash | Method::GetSlot => json!([self.commitment]),
+            Method::GetAccountInfo => json!([self.address().to_string(), accounts]),
+            Method::GetMultipleAccounts => {
+                let addresses = self.addresses.iter().map(Pubkey::to_string);
+                json!([addresses.collect::<Vec<_>>(), accounts])
+            }
+            Method::GetBalance => json!([self.address().to_string(), self.commitment]),
+            Method::GetFeeForMessage => {
+                // Sent with the client's default commitment, as `RpcClient` does
+                let message = encode(&tx().message, UiTransactionEncoding::Base64)?;
+                json!([message, CommitmentConfig::default()])
+            }
+        })
+    }
+}
+
+/// Bincode serializes `input` and encodes it as base58 or base64
+#[allow(clippy::result_large_err)]
+fn encode(input: &impl Serialize, encoding: UiTransactionEncoding) -> ClientResult<String> {
+    let serialized = bincode::serialize(input)
+        .map_err(|err| ClientErrorKind::Custom(format!("serialization failed: {err}")))?;
+    Ok(match encoding {
+        UiTransactionEncoding::Base58 => bs58::encode(serialized).into_string(),
+        _ => base64::encode(serialized),
+    })
+}
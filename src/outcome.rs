@@ -0,0 +1,127 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+
+use crate::{operation::CallResult, rpc_error::INVALID_PARAMS};
+
+/// How a single request ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    /// The node answered with a result
+    Success,
+    /// The node simulated the transaction and it failed
+    TransactionFailed,
+    /// The node answered with a JSON-RPC error
+    RpcError(i64),
+    /// The request timed out before the node answered
+    Timeout,
+    /// No usable answer: connection, io or decoding failures
+    Transport,
+}
+
+impl Outcome {
+    pub fn of_result(result: &CallResult) -> Outcome {
+        match result {
+            Ok(None) => Outcome::Success,
+            Ok(Some(_)) => Outcome::TransactionFailed,
+            Err(err) => Outcome::of_error(err),
+        }
+    }
+
+    pub fn of_error(err: &ClientError) -> Outcome {
+        match err.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+                Outcome::RpcError(*code)
+            }
+            ClientErrorKind::Reqwest(err) if err.is_timeout() => Outcome::Timeout,
+            _ => Outcome::Transport,
+        }
+    }
+
+    /// Whether the node answered the request with a result
+    pub fn is_response(self) -> bool {
+        matches!(self, Outcome::Success | Outcome::TransactionFailed)
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::TransactionFailed => write!(f, "tx-failed"),
+            Outcome::RpcError(code) => write!(f, "rpc-error {code}"),
+            Outcome::Timeout => write!(f, "timeout"),
+            Outcome::Transport => write!(f, "transport"),
+        }
+    }
+}
+
+/// Outcomes a run is expected to produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expect {
+    /// rpc-error -32602 for the empty workload, response otherwise
+    Auto,
+    /// Anything, never flagging a mismatch
+    Any,
+    /// A result, whether or not a simulated transaction succeeded
+    Response,
+    /// A result, where a simulated transaction succeeded
+    Success,
+    /// A JSON-RPC error, optionally with a specific `--expect-code`
+    RpcError,
+}
+
+/// Policy deciding which outcomes are reported as unexpected
+#[derive(Debug, Clone, Copy)]
+pub struct Expectation {
+    expect: Expect,
+    code: Option<i64>,
+}
+
+impl Expectation {
+    /// Resolves [`Expect::Auto`] based on whether the operation is `rejected`
+    pub fn new(expect: Expect, code: Option<i64>, rejected: bool) -> Expectation {
+        match expect {
+            Expect::Auto if rejected => Expectation {
+                expect: Expect::RpcError,
+                code: code.or(Some(INVALID_PARAMS)),
+            },
+            Expect::Auto => Expectation {
+                expect: Expect::Response,
+                code,
+            },
+            expect => Expectation { expect, code },
+        }
+    }
+
+    pub fn matches(&self, outcome: Outcome) -> bool {
+        match self.expect {
+            Expect::Auto => unreachable!("resolved in Expectation::new"),
+            Expect::Any => true,
+            Expect::Response => outcome.is_response(),
+            Expect::Success => outcome == Outcome::Success,
+            Expect::RpcError => match outcome {
+                Outcome::RpcError(code) => self.code.is_none() || self.code == Some(code),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.expect, self.code) {
+            (Expect::RpcError, Some(code)) => write!(f, "rpc-error {code}"),
+            (expect, _) => {
+                let value = expect.to_possible_value().expect("no skipped variants");
+                write!(f, "{}", value.get_name())
+            }
+        }
+    }
+}
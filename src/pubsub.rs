@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use futures_util::{future::join_all, StreamExt};
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use solana_client::nonblocking::pubsub_client::{PubsubClient, PubsubClientError};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::stats::{micros, new_histogram};
+
+/// Pubsub subscriptions that can be benchmarked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Subscription {
+    /// A notification per processed slot
+    Slot,
+    /// A notification whenever `--account` changes
+    Account,
+    /// A single notification once `--signature` is processed
+    Signature,
+}
+
+impl fmt::Display for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subscription::Slot => write!(f, "slotSubscribe"),
+            Subscription::Account => write!(f, "accountSubscribe"),
+            Subscription::Signature => write!(f, "signatureSubscribe"),
+        }
+    }
+}
+
+/// What to subscribe to and for how long
+pub struct PubsubOptions {
+    pub subscription: Subscription,
+    /// Concurrent subscriptions, all sharing one connection
+    pub subscriptions: u32,
+    /// How long each subscription listens after it's confirmed
+    pub window: Duration,
+    pub account: Pubkey,
+    pub signature: Signature,
+}
+
+/// Notification timings of one subscription
+struct Listened {
+    /// Time for the node to confirm the subscription
+    subscribe: Duration,
+    /// Arrival times of notifications, since the subscription was confirmed
+    arrivals: Vec<Duration>,
+}
+
+/// Subscribe latencies and notification timings across all subscriptions
+pub struct PubsubStats {
+    pub window: Duration,
+    pub subscribe: Histogram<u64>,
+    /// Time from confirming a subscription to its first notification
+    pub first_notification: Histogram<u64>,
+    /// Time between consecutive notifications of a subscription
+    pub intervals: Histogram<u64>,
+    pub notifications: u64,
+    /// Mean absolute difference between consecutive intervals, in micros
+    pub jitter: f64,
+    /// Subscriptions that failed, by error
+    pub failures: BTreeMap<String, u64>,
+}
+
+impl PubsubStats {
+    fn new(window: Duration) -> PubsubStats {
+        PubsubStats {
+            window,
+            subscribe: new_histogram(),
+            first_notification: new_histogram(),
+            intervals: new_histogram(),
+            notifications: 0,
+            jitter: 0.0,
+            failures: BTreeMap::new(),
+        }
+    }
+
+    /// Notifications per second received by an average subscription
+    pub fn rate(&self) -> f64 {
+        match self.subscribe.len() {
+            0 => 0.0,
+            subscribed => self.notifications as f64 / subscribed as f64 / self.window.as_secs_f64(),
+        }
+    }
+}
+
+/// Opens every subscription on one client, listens for the window and
+/// unsubscribes. Fails if the client can't connect; failed subscriptions are
+/// counted in the stats instead.
+pub async fn run(url: &str, options: &PubsubOptions) -> Result<PubsubStats, PubsubClientError> {
+    let client = PubsubClient::new(url).await?;
+    let listened = join_all((0..options.subscriptions).map(|_| listen(&client, options))).await;
+    client.shutdown().await.ok();
+
+    let mut stats = PubsubStats::new(options.window);
+    let (mut deviation, mut deviations) = (0, 0);
+    for listened in listened {
+        let listened = match listened {
+            Ok(listened) => listened,
+            Err(err) => {
+                *stats.failures.entry(err.to_string()).or_default() += 1;
+                continue;
+            }
+        };
+        stats
+            .subscribe
+            .saturating_record(micros(listened.subscribe));
+        if let Some(&first) = listened.arrivals.first() {
+            stats.first_notification.saturating_record(micros(first));
+        }
+        stats.notifications += listened.arrivals.len() as u64;
+        let intervals = listened
+            .arrivals
+            .windows(2)
+            .map(|pair| micros(pair[1] - pair[0]))
+            .collect::<Vec<_>>();
+        for &interval in &intervals {
+            stats.intervals.saturating_record(interval);
+        }
+        for pair in intervals.windows(2) {
+            deviation += pair[1].abs_diff(pair[0]);
+            deviations += 1;
+        }
+    }
+    if deviations > 0 {
+        stats.jitter = deviation as f64 / deviations as f64;
+    }
+    Ok(stats)
+}
+
+async fn listen(
+    client: &PubsubClient,
+    options: &PubsubOptions,
+) -> Result<Listened, PubsubClientError> {
+    let start = Instant::now();
+    let (mut notifications, unsubscribe) = match options.subscription {
+        Subscription::Slot => {
+            let (notifications, unsubscribe) = client.slot_subscribe().await?;
+            (notifications.map(drop).boxed(), unsubscribe)
+        }
+        Subscription::Account => {
+            let (notifications, unsubscribe) =
+                client.account_subscribe(&options.account, None).await?;
+            (notifications.map(drop).boxed(), unsubscribe)
+        }
+        Subscription::Signature => {
+            let (notifications, unsubscribe) =
+                client.signature_subscribe(&options.signature, None).await?;
+            (notifications.map(drop).boxed(), unsubscribe)
+        }
+    };
+    let subscribed = Instant::now();
+
+    let deadline = tokio::time::Instant::from_std(subscribed + options.window);
+    let mut arrivals = vec![];
+    while let Ok(Some(())) = tokio::time::timeout_at(deadline, notifications.next()).await {
+        arrivals.push(subscribed.elapsed());
+        if options.subscription == Subscription::Signature {
+            break;
+        }
+    }
+    drop(notifications);
+    unsubscribe().await;
+
+    Ok(Listened {
+        subscribe: subscribed - start,
+        arrivals,
+    })
+}
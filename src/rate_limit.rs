@@ -0,0 +1,68 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Slowest rate accepted, one request every 1000s, so that an interval times
+/// any burst still fits in a `Duration`
+pub const MIN_RATE: f64 = 1e-3;
+
+/// Client-side token bucket shared by every request issuer.
+///
+/// Implemented as a reservation scheduler (GCRA): each caller reserves the
+/// next free slot and then waits until it, so concurrent callers are spaced
+/// out deterministically instead of racing for tokens.
+pub struct RateLimiter {
+    /// Time to refill one token
+    interval: Duration,
+    /// How far ahead of the steady rate a request may go, i.e. `burst - 1` tokens
+    tolerance: Duration,
+    /// Theoretical arrival time of the next request at the steady rate
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Allows `requests_per_second` on average with bursts of up to `burst` requests
+    ///
+    /// Panics if `requests_per_second` is below [`MIN_RATE`]
+    pub fn new(requests_per_second: f64, burst: u32) -> RateLimiter {
+        assert!(
+            requests_per_second >= MIN_RATE,
+            "rate {requests_per_second} is below {MIN_RATE}"
+        );
+        let interval = Duration::from_secs_f64(1.0 / requests_per_second);
+        RateLimiter {
+            interval,
+            tolerance: interval * burst.saturating_sub(1),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Reserves a token, returning how long the caller must wait before sending
+    pub fn reserve(&self) -> Duration {
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap();
+        let tat = next.map_or(now, |next| next.max(now));
+        let send_at = tat
+            .checked_sub(self.tolerance)
+            .map_or(now, |earliest| earliest.max(now));
+        *next = Some(tat + self.interval);
+        send_at - now
+    }
+
+    /// Blocks the current thread until a token is available
+    pub fn acquire_blocking(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Waits asynchronously until a token is available
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde_json::Value;
+use solana_client::{
+    client_error::{
+        reqwest::{self, header::CONTENT_TYPE},
+        Result as ClientResult,
+    },
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+};
+use tracing::{info_span, Instrument};
+
+/// Bare JSON-RPC over the same reqwest stack `RpcClient` uses, without its
+/// cluster version query, retries on 429 or typed response decoding.
+///
+/// Each request is traced in `serialize`, `send` (until the response head
+/// arrives), `await` (the response body) and `deserialize` spans.
+pub struct RawClient {
+    client: reqwest::Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+#[allow(clippy::result_large_err)]
+impl RawClient {
+    pub fn new_with_timeout(url: String, timeout: Duration) -> RawClient {
+        RawClient {
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("failed to build http client"),
+            url,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Posts one request, returning its `result` or its JSON-RPC error.
+    /// `params` runs in the `serialize` span.
+    pub async fn send(
+        &self,
+        request: RpcRequest,
+        params: impl FnOnce() -> ClientResult<Value>,
+    ) -> ClientResult<Value> {
+        let body = info_span!("serialize").in_scope(|| {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            ClientResult::Ok(request.build_request_json(id, params()?).to_string())
+        })?;
+        let json = self.post(body).await?;
+        into_result(json)
+    }
+
+    /// Posts `requests` as one JSON-RPC batch, returning each request's
+    /// `result` or JSON-RPC error in order. Fails as a whole if the request
+    /// fails or the node rejects the batch itself. `requests` runs in the
+    /// `serialize` span.
+    pub async fn send_batch(
+        &self,
+        requests: impl FnOnce() -> ClientResult<Vec<(RpcRequest, Value)>>,
+    ) -> ClientResult<Vec<ClientResult<Value>>> {
+        let (ids, body) = info_span!("serialize").in_scope(|| {
+            let requests = requests()?;
+            let first = self
+                .next_id
+                .fetch_add(requests.len() as u64, Ordering::Relaxed);
+            let ids = first..first + requests.len() as u64;
+            let batch = ids
+                .clone()
+                .zip(requests)
+                .map(|(id, (request, params))| request.build_request_json(id, params))
+                .collect();
+            ClientResult::Ok((ids, Value::Array(batch).to_string()))
+        })?;
+        let responses = match self.post(body).await? {
+            Value::Array(responses) => responses,
+            // Nodes without batch support answer with a single error
+            json => {
+                into_result(json)?;
+                return Err(
+                    RpcError::ParseError("batch answered with one result".to_string()).into(),
+                );
+            }
+        };
+        // Nodes may answer a batch in any order
+        let mut responses = responses
+            .into_iter()
+            .filter_map(|response| Some((response.get("id")?.as_u64()?, response)))
+            .collect::<HashMap<_, _>>();
+        Ok(ids
+            .map(|id| match responses.remove(&id) {
+                Some(response) => into_result(response),
+                None => Err(RpcError::ParseError(format!("no response to request {id}")).into()),
+            })
+            .collect())
+    }
+
+    async fn post(&self, body: String) -> ClientResult<Value> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .instrument(info_span!("send"))
+            .await?
+            .error_for_status()?;
+        let bytes = response.bytes().instrument(info_span!("await")).await?;
+        Ok(info_span!("deserialize").in_scope(|| serde_json::from_slice(&bytes))?)
+    }
+}
+
+/// Takes the `result` of a JSON-RPC response, or converts its error
+#[allow(clippy::result_large_err)]
+fn into_result(mut json: Value) -> ClientResult<Value> {
+    if let Some(error) = json.get("error") {
+        return Err(RpcError::RpcResponseError {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+            data: RpcResponseErrorData::Empty,
+        }
+        .into());
+    }
+    match json.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(RpcError::ParseError(format!("no result in {json}")).into()),
+    }
+}
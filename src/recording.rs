@@ -0,0 +1,342 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::{body::Bytes, header::CONTENT_TYPE, Body, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::client_error::reqwest;
+
+use crate::{
+    mock_server::{error_response, ServerThread},
+    rpc_error::{INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR},
+};
+
+/// One JSON-RPC call and what the node answered it with, a line of a
+/// recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    pub reply: Reply,
+    /// Time the node took to answer the http request carrying the call
+    pub latency_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reply {
+    /// The call's JSON-RPC response
+    Json(Value),
+    /// An answer to the whole http request that isn't JSON-RPC, like a 503
+    /// or a body that doesn't parse
+    Http { status: u16, body: String },
+}
+
+/// Appends exchanges to a recording file, as JSON lines
+pub struct Recorder {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Creates the recording at `path`, replacing any file there
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        Ok(Recorder {
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Records every call in the http request `body`, paired with its part of
+    /// the node's answer
+    pub fn record(
+        &self,
+        body: &[u8],
+        status: StatusCode,
+        response: &[u8],
+        latency: Duration,
+    ) -> io::Result<()> {
+        let calls = match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Array(calls)) => calls,
+            Ok(call) => vec![call],
+            // Nothing a replay could match it against
+            Err(_) => return Ok(()),
+        };
+        let mut responses = match serde_json::from_slice::<Value>(response) {
+            Ok(Value::Array(responses)) if status.is_success() => responses,
+            Ok(response) if status.is_success() => vec![response],
+            _ => vec![],
+        };
+
+        let mut file = self.file.lock().unwrap();
+        for call in calls {
+            let id = call.get("id").cloned().unwrap_or(Value::Null);
+            // Batch responses may come in any order
+            let reply = match responses
+                .iter()
+                .position(|response| response.get("id") == Some(&id))
+            {
+                Some(i) => Reply::Json(responses.swap_remove(i)),
+                None => Reply::Http {
+                    status: status.as_u16(),
+                    body: String::from_utf8_lossy(response).into_owned(),
+                },
+            };
+            let exchange = Exchange {
+                method: call["method"].as_str().unwrap_or_default().to_string(),
+                params: call.get("params").cloned().unwrap_or(Value::Null),
+                reply,
+                latency_us: latency.as_micros() as u64,
+            };
+            serde_json::to_writer(&mut *file, &exchange)?;
+            writeln!(file)?;
+        }
+        file.flush()
+    }
+}
+
+/// A local http proxy forwarding JSON-RPC requests to an endpoint and
+/// recording each exchange. It shuts down when dropped.
+pub struct RecordingProxy {
+    server: ServerThread,
+}
+
+impl RecordingProxy {
+    /// Binds `addr` (use port 0 for an ephemeral port) and forwards to
+    /// `upstream`, timing out requests after `timeout`
+    pub fn start(
+        addr: SocketAddr,
+        upstream: String,
+        timeout: Duration,
+        recorder: Arc<Recorder>,
+    ) -> io::Result<RecordingProxy> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(io::Error::other)?;
+        let upstream = Arc::new(upstream);
+        let server = ServerThread::spawn("record-proxy", addr, move |req| {
+            forward(
+                client.clone(),
+                Arc::clone(&upstream),
+                Arc::clone(&recorder),
+                req,
+            )
+        })?;
+        Ok(RecordingProxy { server })
+    }
+
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+}
+
+/// Forwards one http request upstream and records it. Failing to reach the
+/// endpoint drops the client's connection, leaving nothing to record.
+async fn forward(
+    client: reqwest::Client,
+    upstream: Arc<String>,
+    recorder: Arc<Recorder>,
+    req: Request<Body>,
+) -> io::Result<Response<Body>> {
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .map_err(io::Error::other)?;
+    let started = Instant::now();
+    let response = client
+        .post(upstream.as_str())
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.clone())
+        .send()
+        .await
+        .map_err(io::Error::other)?;
+    let status = StatusCode::from_u16(response.status().as_u16()).map_err(io::Error::other)?;
+    let response = response.bytes().await.map_err(io::Error::other)?;
+    let latency = started.elapsed();
+
+    if let Err(err) = recorder.record(&body, status, &response, latency) {
+        eprintln!("failed to write recording: {err}");
+    }
+    Ok(Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(response))
+        .expect("valid response"))
+}
+
+/// Recorded exchanges, looked up by call
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    exchanges: Vec<Exchange>,
+    /// Exchanges by method and params
+    by_call: HashMap<String, Vec<usize>>,
+    by_method: HashMap<String, Vec<usize>>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> io::Result<Recording> {
+        let mut recording = Recording::default();
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange = serde_json::from_str(&line).map_err(|err| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {err}", i + 1))
+            })?;
+            recording.push(exchange);
+        }
+        Ok(recording)
+    }
+
+    pub fn push(&mut self, exchange: Exchange) {
+        let i = self.exchanges.len();
+        self.by_call
+            .entry(call_key(&exchange.method, &exchange.params))
+            .or_default()
+            .push(i);
+        self.by_method
+            .entry(exchange.method.clone())
+            .or_default()
+            .push(i);
+        self.exchanges.push(exchange);
+    }
+
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+}
+
+fn call_key(method: &str, params: &Value) -> String {
+    format!("{method} {params}")
+}
+
+/// A local JSON-RPC server answering with recorded responses, so a run
+/// against a live endpoint can be repeated offline. It shuts down when
+/// dropped.
+///
+/// Calls get the responses recorded for the same method and params, in turn.
+/// Calls never recorded as is, like transactions signed by a fresh payer, get
+/// the responses recorded for their method instead.
+pub struct ReplayServer {
+    server: ServerThread,
+}
+
+impl ReplayServer {
+    /// Binds `addr` (use port 0 for an ephemeral port) and starts serving,
+    /// delaying responses by their recorded latency if `latency` is set
+    pub fn start(
+        addr: SocketAddr,
+        recording: Recording,
+        latency: bool,
+    ) -> io::Result<ReplayServer> {
+        let state = Arc::new(ReplayState {
+            recording,
+            latency,
+            turns: Mutex::default(),
+        });
+        let server = ServerThread::spawn("replay-rpc", addr, move |req| {
+            replay(Arc::clone(&state), req)
+        })?;
+        Ok(ReplayServer { server })
+    }
+
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+}
+
+struct ReplayState {
+    recording: Recording,
+    latency: bool,
+    /// Times each call or method has been answered
+    turns: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayState {
+    /// The exchange whose turn it is to answer `call`
+    fn next(&self, call: &Value) -> Option<&Exchange> {
+        let method = call["method"].as_str()?;
+        let params = call.get("params").cloned().unwrap_or(Value::Null);
+        let key = call_key(method, &params);
+        let (key, indices) = match self.recording.by_call.get(&key) {
+            Some(indices) => (key, indices),
+            None => (method.to_string(), self.recording.by_method.get(method)?),
+        };
+        let mut turns = self.turns.lock().unwrap();
+        let turn = turns.entry(key).or_default();
+        let i = indices[*turn % indices.len()];
+        *turn += 1;
+        Some(&self.recording.exchanges[i])
+    }
+}
+
+/// Answers one http request with the recorded responses to its calls
+async fn replay(state: Arc<ReplayState>, req: Request<Body>) -> io::Result<Response<Body>> {
+    let body = hyper::body::to_bytes(req.into_body()).await;
+    let (calls, batch) = match body.as_deref().map(serde_json::from_slice::<Value>) {
+        Ok(Ok(Value::Array(calls))) if !calls.is_empty() => (calls, true),
+        Ok(Ok(Value::Array(_))) => {
+            let response = error_response(Value::Null, INVALID_PARAMS, "Invalid request");
+            return Ok(json_response(StatusCode::OK, response.to_string()));
+        }
+        Ok(Ok(call)) => (vec![call], false),
+        _ => {
+            let response = error_response(Value::Null, PARSE_ERROR, "Parse error");
+            return Ok(json_response(StatusCode::OK, response.to_string()));
+        }
+    };
+
+    let mut delay = Duration::ZERO;
+    let mut responses = vec![];
+    let mut http = None;
+    for call in &calls {
+        let id = call.get("id").cloned().unwrap_or(Value::Null);
+        let Some(exchange) = state.next(call) else {
+            let method = call["method"].as_str().unwrap_or_default();
+            let message = format!("No recorded response to {method}");
+            responses.push(error_response(id, METHOD_NOT_FOUND, &message));
+            continue;
+        };
+        delay = delay.max(Duration::from_micros(exchange.latency_us));
+        match &exchange.reply {
+            Reply::Json(response) => {
+                let mut response = response.clone();
+                response["id"] = id;
+                responses.push(response);
+            }
+            // Answers the whole request, as it did when recorded
+            Reply::Http { status, body } => {
+                http.get_or_insert((*status, body.clone()));
+            }
+        }
+    }
+    if state.latency {
+        tokio::time::sleep(delay).await;
+    }
+
+    if let Some((status, body)) = http {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        return Ok(json_response(status, body));
+    }
+    let response = if batch {
+        Value::Array(responses)
+    } else {
+        responses.remove(0)
+    };
+    Ok(json_response(StatusCode::OK, response.to_string()))
+}
+
+fn json_response(status: StatusCode, body: impl Into<Bytes>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.into()))
+        .expect("valid response")
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    baseline::Comparison,
+    cli::{
+        BaselineArgs, BenchArgs, ConcurrencyArgs, FaultArgs, LoadArgs, MethodArgs, Mode,
+        PubsubArgs, RecordArgs, RetryArgs, SimulateArgs, ThresholdArgs, WorkloadArgs,
+    },
+    outcome::Expectation,
+    pubsub::PubsubStats,
+    stats::{Iteration, IterationSummary, LatencySummary, RunStats},
+    taxonomy::ErrorKey,
+    threshold::Violation,
+};
+
+/// Machine-readable record of a benchmark run
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub config: ReportConfig,
+    pub environment: Environment,
+    pub modes: Vec<ModeReport>,
+    /// Changes since `config.baseline.baseline`, if set
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub comparison: Vec<Comparison>,
+    /// Modes that broke `config.thresholds`
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    pub fn new(args: &BenchArgs, expectation: &Expectation) -> Report {
+        Report {
+            config: ReportConfig {
+                urls: args.urls.clone(),
+                requests: args.requests,
+                warmup: args.warmup,
+                iterations: args.iterations,
+                modes: args.modes.clone(),
+                batch_size: args.batch_size,
+                rps: args.rps,
+                burst: args.burst,
+                mock: args.mock,
+                timeout_secs: args.timeout,
+                expect: expectation.to_string(),
+                workload: args.workload.clone(),
+                method: args.method.clone(),
+                simulate: args.simulate.clone(),
+                load: args.load.clone(),
+                concurrency: args.concurrency.clone(),
+                retry: args.retry.clone(),
+                thresholds: args.thresholds.clone(),
+                faults: args.faults.clone(),
+                record: args.record.clone(),
+                baseline: args.baseline.clone(),
+            },
+            environment: Environment::current(),
+            modes: vec![],
+            comparison: vec![],
+            violations: vec![],
+        }
+    }
+
+    /// Adds a mode's `stats`, combined across its `iterations`
+    pub fn push(&mut self, endpoint: &str, mode: Mode, stats: &RunStats, iterations: &[Iteration]) {
+        self.modes.push(ModeReport {
+            endpoint: endpoint.to_string(),
+            mode,
+            requests: stats.latencies.len(),
+            total_micros: stats.elapsed.as_micros() as u64,
+            throughput: stats.throughput(),
+            error_rate: stats.error_rate(),
+            latency_micros: stats.summary(),
+            first_try_latency_micros: LatencySummary::from_histogram(&stats.first_try),
+            errors: stats.errors,
+            outcomes: stats
+                .outcomes
+                .iter()
+                .map(|(outcome, count)| (outcome.to_string(), *count))
+                .collect(),
+            unexpected: stats.unexpected,
+            error_breakdown: stats
+                .error_breakdown
+                .iter()
+                .map(|(error, count)| ErrorCount {
+                    error: error.clone(),
+                    count: *count,
+                })
+                .collect(),
+            retries: stats.retries,
+            retried_requests: stats.retried,
+            iterations: iterations.to_vec(),
+            iteration_summary: IterationSummary::of(iterations),
+        });
+    }
+
+    /// Writes pretty-printed json to `path`, or to stdout if `path` is `-`
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        write_json(path, self)
+    }
+}
+
+/// Machine-readable record of a pubsub benchmark
+#[derive(Debug, Serialize)]
+pub struct PubsubReport<'a> {
+    pub config: &'a PubsubArgs,
+    pub environment: Environment,
+    pub subscribed: u64,
+    pub subscribe_latency_micros: LatencySummary,
+    pub first_notification_micros: LatencySummary,
+    pub interval_micros: LatencySummary,
+    pub notifications: u64,
+    /// Notifications per second received by an average subscription
+    pub notifications_per_second: f64,
+    pub jitter_micros: f64,
+    /// Subscriptions that failed, by error
+    pub failures: BTreeMap<String, u64>,
+}
+
+impl PubsubReport<'_> {
+    pub fn new<'a>(args: &'a PubsubArgs, stats: &PubsubStats) -> PubsubReport<'a> {
+        PubsubReport {
+            config: args,
+            environment: Environment::current(),
+            subscribed: stats.subscribe.len(),
+            subscribe_latency_micros: LatencySummary::from_histogram(&stats.subscribe),
+            first_notification_micros: LatencySummary::from_histogram(&stats.first_notification),
+            interval_micros: LatencySummary::from_histogram(&stats.intervals),
+            notifications: stats.notifications,
+            notifications_per_second: stats.rate(),
+            jitter_micros: stats.jitter,
+            failures: stats.failures.clone(),
+        }
+    }
+
+    /// Writes pretty-printed json to `path`, or to stdout if `path` is `-`
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        write_json(path, self)
+    }
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> io::Result<()> {
+    let mut writer: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(BufWriter::new(File::create(path)?))
+    };
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportConfig {
+    pub urls: Vec<String>,
+    pub requests: u64,
+    pub warmup: u64,
+    pub iterations: u32,
+    pub modes: Vec<Mode>,
+    pub batch_size: u64,
+    pub rps: Option<f64>,
+    pub burst: u32,
+    /// Number of embedded mock servers benchmarked
+    pub mock: Option<u16>,
+    pub timeout_secs: u64,
+    /// Outcome every request was expected to have
+    pub expect: String,
+    pub workload: WorkloadArgs,
+    pub method: MethodArgs,
+    pub simulate: SimulateArgs,
+    pub load: LoadArgs,
+    pub concurrency: ConcurrencyArgs,
+    pub retry: RetryArgs,
+    pub thresholds: ThresholdArgs,
+    /// Faults injected by the embedded mocks
+    pub faults: FaultArgs,
+    pub record: RecordArgs,
+    pub baseline: BaselineArgs,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Environment {
+    pub tx_sim_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpus: usize,
+    pub unix_timestamp: u64,
+}
+
+impl Environment {
+    fn current() -> Environment {
+        Environment {
+            tx_sim_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            unix_timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModeReport {
+    pub endpoint: String,
+    pub mode: Mode,
+    pub requests: u64,
+    pub total_micros: u64,
+    pub throughput: f64,
+    /// Fraction of requests the node didn't answer with a result
+    pub error_rate: f64,
+    pub latency_micros: LatencySummary,
+    pub first_try_latency_micros: LatencySummary,
+    pub errors: u64,
+    /// Request count by outcome
+    pub outcomes: BTreeMap<String, u64>,
+    /// Requests whose outcome didn't match `config.expect`
+    pub unexpected: u64,
+    /// Request count by failure, including simulations that ran but failed
+    pub error_breakdown: Vec<ErrorCount>,
+    pub retries: u64,
+    pub retried_requests: u64,
+    pub iterations: Vec<Iteration>,
+    /// Variation across iterations, if there were several
+    pub iteration_summary: Option<IterationSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorCount {
+    #[serde(flatten)]
+    pub error: ErrorKey,
+    pub count: u64,
+}
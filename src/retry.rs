@@ -0,0 +1,163 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    client_error::{reqwest::StatusCode, ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_request::RpcError,
+};
+
+use crate::{cli::RetryArgs, rate_limit::RateLimiter, rpc_error::NODE_UNHEALTHY};
+
+/// Prefix of the error the client reports when its node version lookup fails
+const VERSION_QUERY_FAILED: &str = "cluster version query failed";
+
+/// Failures that may succeed if the request is sent again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transient {
+    /// HTTP 429 Too Many Requests
+    RateLimited,
+    /// Connection failures, timeouts, io errors and HTTP 5xx
+    Transport,
+    /// The node reported itself unhealthy (-32005)
+    Unhealthy,
+}
+
+/// Classifies `err` as transient, or `None` if retrying can't help.
+///
+/// Note the client's http sender already retries 429s a few times on its own
+/// before surfacing them, so a rate limited error here means those ran out.
+/// Simulation also looks up the node version on a client's first request,
+/// which flattens any transport failure into a request error string.
+pub fn classify(err: &ClientError) -> Option<Transient> {
+    match err.kind() {
+        ClientErrorKind::Reqwest(err) => match err.status() {
+            Some(StatusCode::TOO_MANY_REQUESTS) => Some(Transient::RateLimited),
+            Some(status) if status.is_server_error() => Some(Transient::Transport),
+            Some(_) => None,
+            // Includes connections closed before or while the response arrived
+            None if err.is_connect() || err.is_timeout() || err.is_request() || err.is_body() => {
+                Some(Transient::Transport)
+            }
+            None => None,
+        },
+        ClientErrorKind::Io(_) => Some(Transient::Transport),
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(message))
+            if message.starts_with(VERSION_QUERY_FAILED) =>
+        {
+            Some(Transient::Transport)
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == NODE_UNHEALTHY =>
+        {
+            Some(Transient::Unhealthy)
+        }
+        _ => None,
+    }
+}
+
+/// Outcome of a request along with how many attempts it took
+pub struct Attempted<T> {
+    pub result: ClientResult<T>,
+    /// Latency of the first attempt alone
+    pub first_try: Duration,
+    pub retries: u32,
+}
+
+/// Exponential backoff over the transient errors selected on the command line
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_on: Vec<Transient>,
+}
+
+impl RetryPolicy {
+    pub fn new(args: &RetryArgs) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: args.max_retries,
+            initial_backoff: Duration::from_millis(args.backoff_ms),
+            max_backoff: Duration::from_millis(args.max_backoff_ms),
+            retry_on: args.retry_on.clone(),
+        }
+    }
+
+    /// Delay before the retry numbered `retry`, counting from zero
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+
+    fn should_retry<T>(&self, result: &ClientResult<T>, retries: u32) -> bool {
+        match result {
+            Err(err) if retries < self.max_retries => {
+                classify(err).is_some_and(|class| self.retry_on.contains(&class))
+            }
+            _ => false,
+        }
+    }
+
+    /// Runs `request` on the current thread until it succeeds, fails
+    /// permanently or runs out of retries. Retries also wait on `limiter`.
+    pub fn run_blocking<T>(
+        &self,
+        limiter: Option<&RateLimiter>,
+        mut request: impl FnMut() -> ClientResult<T>,
+    ) -> Attempted<T> {
+        let mut first_try = None;
+        let mut retries = 0;
+        loop {
+            let attempt_timer = Instant::now();
+            let result = request();
+            let first_try = *first_try.get_or_insert(attempt_timer.elapsed());
+            if !self.should_retry(&result, retries) {
+                return Attempted {
+                    result,
+                    first_try,
+                    retries,
+                };
+            }
+            std::thread::sleep(self.backoff(retries));
+            if let Some(limiter) = limiter {
+                limiter.acquire_blocking();
+            }
+            retries += 1;
+        }
+    }
+
+    /// Async counterpart of [`RetryPolicy::run_blocking`]
+    pub async fn run<T, F, Fut>(
+        &self,
+        limiter: Option<&RateLimiter>,
+        mut request: F,
+    ) -> Attempted<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let mut first_try = None;
+        let mut retries = 0;
+        loop {
+            let attempt_timer = Instant::now();
+            let result = request().await;
+            let first_try = *first_try.get_or_insert(attempt_timer.elapsed());
+            if !self.should_retry(&result, retries) {
+                return Attempted {
+                    result,
+                    first_try,
+                    retries,
+                };
+            }
+            tokio::time::sleep(self.backoff(retries)).await;
+            if let Some(limiter) = limiter {
+                limiter.acquire().await;
+            }
+            retries += 1;
+        }
+    }
+}
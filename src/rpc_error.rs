@@ -0,0 +1,17 @@
+/// Request body isn't valid json
+pub const PARSE_ERROR: i64 = -32700;
+
+/// The rpc doesn't serve the method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Malformed params, or a transaction that fails to decode or sanitize
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// A simulated transaction's signatures don't verify
+pub const SIGNATURE_VERIFICATION_FAILURE: i64 = -32003;
+
+/// The node is too far behind the cluster to serve requests
+pub const NODE_UNHEALTHY: i64 = -32005;
+
+/// The node hasn't reached a request's `minContextSlot`
+pub const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
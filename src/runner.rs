@@ -0,0 +1,269 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use indicatif::ProgressBar;
+use rayon::{
+    prelude::{IntoParallelIterator, ParallelIterator},
+    ThreadPool,
+};
+use solana_client::rpc_client::RpcClient;
+use tokio::{runtime::Runtime, sync::Semaphore, task::JoinSet};
+use tracing::{info_span, Instrument};
+
+use crate::{
+    cli::{ConcurrencyArgs, RuntimeFlavor},
+    operation::{AsyncClient, Operation},
+    outcome::{Expectation, Outcome},
+    rate_limit::RateLimiter,
+    raw_client::RawClient,
+    retry::RetryPolicy,
+    schedule::Schedule,
+    stats::{LatencyRecorder, RunStats, Sample},
+    taxonomy::ErrorKey,
+};
+
+pub fn build_runtime(concurrency: &ConcurrencyArgs) -> io::Result<Runtime> {
+    let mut builder = match concurrency.runtime {
+        RuntimeFlavor::CurrentThread => tokio::runtime::Builder::new_current_thread(),
+        RuntimeFlavor::MultiThread => {
+            let mut builder = tokio::runtime::Builder::new_multi_thread();
+            builder.worker_threads(concurrency.worker_threads);
+            builder
+        }
+    };
+    builder.enable_all().build()
+}
+
+/// Knobs shared by the sync and async runners
+#[derive(Clone)]
+pub struct RunOptions {
+    /// Number of requests to send
+    pub requests: u64,
+    /// Maximum outstanding async requests
+    pub max_in_flight: Option<usize>,
+    /// Paces closed-loop requests, which wait on it before their timer starts
+    pub limiter: Option<Arc<RateLimiter>>,
+    pub retry: Arc<RetryPolicy>,
+    /// Issue requests open-loop at this rate instead of as fast as possible
+    pub arrival_rate: Option<f64>,
+    pub operation: Arc<Operation>,
+    /// Outcome requests are checked against
+    pub expectation: Expectation,
+}
+
+/// Calls the operation on the rayon pool, timing each request.
+///
+/// Closed-loop runs let rayon split the requests across the pool. Open-loop
+/// runs have every pool thread claim the next scheduled request, sleep until
+/// its intended send time and measure latency from then.
+pub fn run_sync(pool: &ThreadPool, client: Arc<RpcClient>, options: &RunOptions) -> RunStats {
+    let pb = ProgressBar::new(options.requests);
+    let recorder = LatencyRecorder::new();
+    let limiter = options.limiter.as_deref();
+    let call = |client: &RpcClient, index: u64, start: Instant| {
+        let _span = info_span!("request", index).entered();
+        #[allow(clippy::result_large_err)]
+        let attempted = options
+            .retry
+            .run_blocking(limiter, || options.operation.call_blocking(client));
+        let outcome = Outcome::of_result(&attempted.result);
+        recorder.record(Sample {
+            latency: start.elapsed(),
+            first_try: attempted.first_try,
+            retries: attempted.retries,
+            outcome,
+            expected: options.expectation.matches(outcome),
+            error: ErrorKey::of_result(&attempted.result),
+        });
+        pb.inc(1);
+    };
+
+    let timer = Instant::now();
+    match options.arrival_rate {
+        None => pool.install(|| {
+            (0..options.requests)
+                .into_par_iter()
+                .for_each_with(client, |client, index| {
+                    if let Some(limiter) = limiter {
+                        limiter.acquire_blocking();
+                    }
+                    call(client, index, Instant::now());
+                })
+        }),
+        Some(rate) => {
+            let schedule = Schedule::starting_now(rate);
+            let next = AtomicU64::new(0);
+            pool.broadcast(|_| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= options.requests {
+                    break;
+                }
+                let intended = schedule.intended(index);
+                if let Some(early) = intended.checked_duration_since(Instant::now()) {
+                    std::thread::sleep(early);
+                }
+                call(&client, index, intended);
+            });
+        }
+    }
+    let elapsed = timer.elapsed();
+    pb.finish();
+    recorder.into_stats(elapsed)
+}
+
+/// Calls the operation from tokio tasks, timing each request.
+///
+/// At most `max_in_flight` requests are outstanding at once, if set. Tasks
+/// are spawned no faster than the limiter allows, or at their intended send
+/// time in open-loop runs, where latency is measured from that time.
+pub fn run_async(runtime: &Runtime, client: Arc<AsyncClient>, options: &RunOptions) -> RunStats {
+    let async_pb = ProgressBar::new(options.requests);
+    let recorder = Arc::new(LatencyRecorder::new());
+    let in_flight = Arc::new(Semaphore::new(
+        options.max_in_flight.unwrap_or(Semaphore::MAX_PERMITS),
+    ));
+    let timer = Instant::now();
+    runtime.block_on(async {
+        let schedule = options.arrival_rate.map(Schedule::starting_now);
+        let mut tasks = JoinSet::new();
+        for index in 0..options.requests {
+            if let Some(schedule) = &schedule {
+                tokio::time::sleep_until(schedule.intended(index).into()).await;
+            }
+            let permit = Arc::clone(&in_flight)
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            if let Some(limiter) = &options.limiter {
+                limiter.acquire().await;
+            }
+            let start = schedule.map_or_else(Instant::now, |schedule| schedule.intended(index));
+            let arc_client = Arc::clone(&client);
+            let pb = async_pb.clone();
+            let recorder = Arc::clone(&recorder);
+            let limiter = options.limiter.clone();
+            let retry = Arc::clone(&options.retry);
+            let operation = Arc::clone(&options.operation);
+            let expectation = options.expectation;
+            // A root span, so the trace gives the task a lane of its own
+            let span = info_span!(parent: None, "request", index);
+            let request = async move {
+                let attempted = retry
+                    .run(limiter.as_deref(), || operation.call(&arc_client))
+                    .await;
+                let outcome = Outcome::of_result(&attempted.result);
+                recorder.record(Sample {
+                    latency: start.elapsed(),
+                    first_try: attempted.first_try,
+                    retries: attempted.retries,
+                    outcome,
+                    expected: expectation.matches(outcome),
+                    error: ErrorKey::of_result(&attempted.result),
+                });
+                drop(permit);
+                pb.inc(1);
+            };
+            tasks.spawn(request.instrument(span));
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.expect("request task panicked");
+        }
+    });
+    let elapsed = timer.elapsed();
+    async_pb.finish();
+    Arc::try_unwrap(recorder)
+        .ok()
+        .expect("all tasks joined")
+        .into_stats(elapsed)
+}
+
+/// Calls the operation in JSON-RPC batches of `batch_size` from tokio tasks.
+///
+/// Every call in a batch shares the batch's latency. Limits and retries apply
+/// to whole batches. Open-loop runs send a batch once its last call is due and
+/// measure each call from its own intended send time, so time spent filling
+/// the batch counts against it.
+pub fn run_batch(
+    runtime: &Runtime,
+    client: Arc<RawClient>,
+    batch_size: u64,
+    options: &RunOptions,
+) -> RunStats {
+    let batch_pb = ProgressBar::new(options.requests);
+    let recorder = Arc::new(LatencyRecorder::new());
+    let in_flight = Arc::new(Semaphore::new(
+        options.max_in_flight.unwrap_or(Semaphore::MAX_PERMITS),
+    ));
+    let timer = Instant::now();
+    runtime.block_on(async {
+        let schedule = options.arrival_rate.map(Schedule::starting_now);
+        let mut tasks = JoinSet::new();
+        for first in (0..options.requests).step_by(batch_size as usize) {
+            let calls = first..options.requests.min(first + batch_size);
+            if let Some(schedule) = &schedule {
+                tokio::time::sleep_until(schedule.intended(calls.end - 1).into()).await;
+            }
+            let permit = Arc::clone(&in_flight)
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            if let Some(limiter) = &options.limiter {
+                limiter.acquire().await;
+            }
+            let now = Instant::now();
+            let starts = calls
+                .map(|index| schedule.map_or(now, |schedule| schedule.intended(index)))
+                .collect::<Vec<_>>();
+            let client = Arc::clone(&client);
+            let pb = batch_pb.clone();
+            let recorder = Arc::clone(&recorder);
+            let limiter = options.limiter.clone();
+            let retry = Arc::clone(&options.retry);
+            let operation = Arc::clone(&options.operation);
+            let expectation = options.expectation;
+            let span = info_span!(parent: None, "batch", first, size = starts.len());
+            let batch = async move {
+                let attempted = retry
+                    .run(limiter.as_deref(), || {
+                        operation.call_batch(&client, starts.len())
+                    })
+                    .await;
+                for (i, start) in starts.iter().enumerate() {
+                    let (outcome, error) = match &attempted.result {
+                        Ok(results) => (
+                            Outcome::of_result(&results[i]),
+                            ErrorKey::of_result(&results[i]),
+                        ),
+                        Err(err) => (Outcome::of_error(err), Some(ErrorKey::of_error(err))),
+                    };
+                    recorder.record(Sample {
+                        latency: start.elapsed(),
+                        first_try: attempted.first_try,
+                        retries: attempted.retries,
+                        outcome,
+                        expected: expectation.matches(outcome),
+                        error,
+                    });
+                }
+                drop(permit);
+                pb.inc(starts.len() as u64);
+            };
+            tasks.spawn(batch.instrument(span));
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.expect("batch task panicked");
+        }
+    });
+    let elapsed = timer.elapsed();
+    batch_pb.finish();
+    Arc::try_unwrap(recorder)
+        .ok()
+        .expect("all tasks joined")
+        .into_stats(elapsed)
+}
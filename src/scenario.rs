@@ -0,0 +1,465 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    cli::{
+        parse_endpoint, AccountEncoding, BenchArgs, Commitment, Mode, RuntimeFlavor, TxEncoding,
+    },
+    fault::{Faults, Latency},
+    operation::Method,
+    outcome::Expect,
+    rate_limit::MIN_RATE,
+    retry::Transient,
+    workload::WorkloadKind,
+};
+
+/// Why a scenario file couldn't be loaded
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    /// Malformed TOML, or a key of the wrong name or type
+    Parse(toml::de::Error),
+    /// A well-formed key with a value `bench` would reject
+    Invalid {
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(err) => write!(f, "{err}"),
+            ScenarioError::Parse(err) => write!(f, "{err}"),
+            ScenarioError::Invalid { field, message } => write!(f, "invalid `{field}`: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+/// A `bench` run described in TOML, so it can be checked in rather than
+/// remembered as flags.
+///
+/// Keys are the `bench` flags, grouped into tables like the `--help` output
+/// groups them. Anything left out takes the flag's default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Scenario {
+    /// Rpc endpoint urls or cluster monikers, like `--url`
+    pub endpoints: Option<Vec<String>>,
+    pub mock: Option<u16>,
+    pub modes: Option<Vec<Mode>>,
+    pub requests: Option<u64>,
+    pub warmup: Option<u64>,
+    pub iterations: Option<u32>,
+    pub batch_size: Option<u64>,
+    pub rps: Option<f64>,
+    pub burst: Option<u32>,
+    pub expect: Option<Expect>,
+    pub expect_code: Option<i64>,
+    /// Seconds before a request times out
+    pub timeout: Option<u64>,
+    pub workload: WorkloadTable,
+    pub method: MethodTable,
+    pub simulate: SimulateTable,
+    pub load: LoadTable,
+    pub concurrency: ConcurrencyTable,
+    pub retry: RetryTable,
+    pub thresholds: ThresholdTable,
+    /// Only applies to the embedded mocks
+    pub faults: FaultTable,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WorkloadTable {
+    pub kind: Option<WorkloadKind>,
+    /// Keypair file, relative to the scenario file
+    pub payer: Option<PathBuf>,
+    pub instructions: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MethodTable {
+    pub name: Option<Method>,
+    pub addresses: Option<Vec<String>>,
+    pub commitment: Option<Commitment>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SimulateTable {
+    pub sig_verify: Option<bool>,
+    pub replace_recent_blockhash: Option<bool>,
+    pub encoding: Option<TxEncoding>,
+    pub accounts: Option<Vec<String>>,
+    pub accounts_encoding: Option<AccountEncoding>,
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LoadTable {
+    pub arrival_rate: Option<f64>,
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConcurrencyTable {
+    pub rayon_threads: Option<usize>,
+    pub runtime: Option<RuntimeFlavor>,
+    pub worker_threads: Option<usize>,
+    pub max_in_flight: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RetryTable {
+    pub max_retries: Option<u32>,
+    pub backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub retry_on: Option<Vec<Transient>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThresholdTable {
+    pub max_p50_us: Option<u64>,
+    pub max_p90_us: Option<u64>,
+    pub max_p99_us: Option<u64>,
+    pub max_error_rate: Option<f64>,
+    pub min_rps: Option<f64>,
+    pub max_unexpected: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FaultTable {
+    /// A distribution like `--fault-latency`, such as `"lognormal:5,0.5"`
+    pub latency: Option<String>,
+    pub invalid_params: Option<f64>,
+    pub unhealthy: Option<f64>,
+    pub rate_limited: Option<f64>,
+    pub unavailable: Option<f64>,
+    pub malformed: Option<f64>,
+    pub truncated: Option<f64>,
+    pub drop: Option<f64>,
+}
+
+impl Scenario {
+    /// Reads the scenario at `path` into the `bench` arguments it describes
+    pub fn load(path: &Path) -> Result<BenchArgs, ScenarioError> {
+        let text = fs::read_to_string(path).map_err(ScenarioError::Io)?;
+        Scenario::parse(&text, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parses a scenario into `bench` arguments, resolving relative paths
+    /// against `dir`
+    pub fn parse(text: &str, dir: &Path) -> Result<BenchArgs, ScenarioError> {
+        let scenario: Scenario = toml::from_str(text).map_err(ScenarioError::Parse)?;
+        scenario.into_args(dir)
+    }
+
+    /// Overrides the default arguments with every key set, checking them the
+    /// way the flags' parsers would
+    fn into_args(self, dir: &Path) -> Result<BenchArgs, ScenarioError> {
+        let mut args = BenchArgs::default();
+
+        if let Some(endpoints) = self.endpoints {
+            require(self.mock.is_none(), "mock", "conflicts with `endpoints`")?;
+            require(!endpoints.is_empty(), "endpoints", "lists no endpoints")?;
+            args.urls = endpoints
+                .iter()
+                .enumerate()
+                .map(|(i, url)| {
+                    parse_endpoint(url).map_err(|err| invalid(index("endpoints", i), err))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(mock) = self.mock {
+            require(mock >= 1, "mock", "must be at least 1")?;
+            args.mock = Some(mock);
+        }
+        if let Some(modes) = self.modes {
+            require(!modes.is_empty(), "modes", "lists no modes")?;
+            args.modes = modes;
+        }
+        if let Some(requests) = self.requests {
+            require(
+                self.load.duration.is_none(),
+                "requests",
+                "conflicts with `load.duration`",
+            )?;
+            require(requests >= 1, "requests", "must be at least 1")?;
+            args.requests = requests;
+        }
+        set(&mut args.warmup, self.warmup);
+        if let Some(iterations) = self.iterations {
+            require(iterations >= 1, "iterations", "must be at least 1")?;
+            args.iterations = iterations;
+        }
+        if let Some(batch_size) = self.batch_size {
+            require(batch_size >= 1, "batch-size", "must be at least 1")?;
+            args.batch_size = batch_size;
+        }
+        if let Some(rps) = self.rps {
+            require_rate(rps, "rps")?;
+            require(
+                self.load.arrival_rate.is_none(),
+                "rps",
+                "conflicts with `load.arrival-rate`",
+            )?;
+            args.rps = Some(rps);
+        }
+        if let Some(burst) = self.burst {
+            require(burst >= 1, "burst", "must be at least 1")?;
+            require(self.rps.is_some(), "burst", "requires `rps`")?;
+            args.burst = burst;
+        }
+        set(&mut args.expect, self.expect);
+        args.expect_code = self.expect_code.or(args.expect_code);
+        if let Some(timeout) = self.timeout {
+            require(timeout >= 1, "timeout", "must be at least 1")?;
+            args.timeout = timeout;
+        }
+
+        let workload = self.workload;
+        set(&mut args.workload.workload, workload.kind);
+        if let Some(payer) = workload.payer {
+            args.workload.payer = Some(dir.join(payer));
+        }
+        if let Some(instructions) = workload.instructions {
+            require(
+                instructions >= 1,
+                "workload.instructions",
+                "must be at least 1",
+            )?;
+            args.workload.instructions = instructions;
+        }
+
+        let method = self.method;
+        set(&mut args.method.method, method.name);
+        if let Some(addresses) = method.addresses {
+            require(
+                !addresses.is_empty(),
+                "method.addresses",
+                "lists no accounts",
+            )?;
+            args.method.addresses = parse_pubkeys("method.addresses", &addresses)?;
+        }
+        args.method.commitment = method.commitment.or(args.method.commitment);
+
+        let simulate = self.simulate;
+        set(&mut args.simulate.sig_verify, simulate.sig_verify);
+        if let Some(replace) = simulate.replace_recent_blockhash {
+            require(
+                !(replace && args.simulate.sig_verify),
+                "simulate.replace-recent-blockhash",
+                "conflicts with `simulate.sig-verify`",
+            )?;
+            args.simulate.replace_recent_blockhash = replace;
+        }
+        args.simulate.encoding = simulate.encoding.or(args.simulate.encoding);
+        if let Some(accounts) = simulate.accounts {
+            args.simulate.accounts = parse_pubkeys("simulate.accounts", &accounts)?;
+        }
+        if let Some(encoding) = simulate.accounts_encoding {
+            require(
+                !args.simulate.accounts.is_empty(),
+                "simulate.accounts-encoding",
+                "requires `simulate.accounts`",
+            )?;
+            args.simulate.accounts_encoding = Some(encoding);
+        }
+        args.simulate.min_context_slot =
+            simulate.min_context_slot.or(args.simulate.min_context_slot);
+
+        if let Some(rate) = self.load.arrival_rate {
+            require_rate(rate, "load.arrival-rate")?;
+            args.load.arrival_rate = Some(rate);
+        }
+        if let Some(duration) = self.load.duration {
+            require(
+                positive(duration),
+                "load.duration",
+                "must be a positive number of seconds",
+            )?;
+            require(
+                args.load.arrival_rate.is_some(),
+                "load.duration",
+                "requires `load.arrival-rate`",
+            )?;
+            args.load.duration = Some(duration);
+        }
+
+        let concurrency = self.concurrency;
+        if let Some(threads) = concurrency.rayon_threads {
+            require(
+                threads >= 1,
+                "concurrency.rayon-threads",
+                "must be at least 1",
+            )?;
+            args.concurrency.rayon_threads = threads;
+        }
+        set(&mut args.concurrency.runtime, concurrency.runtime);
+        if let Some(threads) = concurrency.worker_threads {
+            require(
+                threads >= 1,
+                "concurrency.worker-threads",
+                "must be at least 1",
+            )?;
+            args.concurrency.worker_threads = threads;
+        }
+        if let Some(max_in_flight) = concurrency.max_in_flight {
+            require(
+                max_in_flight >= 1,
+                "concurrency.max-in-flight",
+                "must be at least 1",
+            )?;
+            args.concurrency.max_in_flight = Some(max_in_flight);
+        }
+
+        let retry = self.retry;
+        set(&mut args.retry.max_retries, retry.max_retries);
+        set(&mut args.retry.backoff_ms, retry.backoff_ms);
+        set(&mut args.retry.max_backoff_ms, retry.max_backoff_ms);
+        set(&mut args.retry.retry_on, retry.retry_on);
+
+        let thresholds = self.thresholds;
+        args.thresholds.max_p50_us = thresholds.max_p50_us;
+        args.thresholds.max_p90_us = thresholds.max_p90_us;
+        args.thresholds.max_p99_us = thresholds.max_p99_us;
+        if let Some(rate) = thresholds.max_error_rate {
+            let message = "must be a fraction between 0 and 1";
+            require(
+                (0.0..=1.0).contains(&rate),
+                "thresholds.max-error-rate",
+                message,
+            )?;
+            args.thresholds.max_error_rate = Some(rate);
+        }
+        if let Some(rps) = thresholds.min_rps {
+            let message = "must be a positive number of requests per second";
+            require(positive(rps), "thresholds.min-rps", message)?;
+            args.thresholds.min_rps = Some(rps);
+        }
+        args.thresholds.max_unexpected = thresholds.max_unexpected;
+
+        let faults = self.faults;
+        if let Some(latency) = faults.latency {
+            require(self.mock.is_some(), "faults.latency", "requires `mock`")?;
+            let latency = latency
+                .parse::<Latency>()
+                .map_err(|err| invalid("faults.latency", err))?;
+            args.faults.fault_latency = Some(latency);
+        }
+        for (key, probability, arg) in [
+            (
+                "faults.invalid-params",
+                faults.invalid_params,
+                &mut args.faults.fault_invalid_params,
+            ),
+            (
+                "faults.unhealthy",
+                faults.unhealthy,
+                &mut args.faults.fault_unhealthy,
+            ),
+            (
+                "faults.rate-limited",
+                faults.rate_limited,
+                &mut args.faults.fault_rate_limited,
+            ),
+            (
+                "faults.unavailable",
+                faults.unavailable,
+                &mut args.faults.fault_unavailable,
+            ),
+            (
+                "faults.malformed",
+                faults.malformed,
+                &mut args.faults.fault_malformed,
+            ),
+            (
+                "faults.truncated",
+                faults.truncated,
+                &mut args.faults.fault_truncated,
+            ),
+            ("faults.drop", faults.drop, &mut args.faults.fault_drop),
+        ] {
+            let Some(probability) = probability else {
+                continue;
+            };
+            require(self.mock.is_some(), key, "requires `mock`")?;
+            let message = "must be a fraction between 0 and 1";
+            require((0.0..=1.0).contains(&probability), key, message)?;
+            *arg = probability;
+        }
+        Faults::new(&args.faults).map_err(|err| invalid("faults", err))?;
+
+        Ok(args)
+    }
+}
+
+fn set<T>(arg: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *arg = value;
+    }
+}
+
+fn positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// Checks `value` is a rate requests can be sent at
+fn require_rate(value: f64, field: &str) -> Result<(), ScenarioError> {
+    if value.is_finite() && value >= MIN_RATE {
+        Ok(())
+    } else {
+        Err(invalid(
+            field,
+            format!("must be at least {MIN_RATE} requests per second"),
+        ))
+    }
+}
+
+fn invalid(field: impl Into<String>, message: impl Into<String>) -> ScenarioError {
+    ScenarioError::Invalid {
+        field: field.into(),
+        message: message.into(),
+    }
+}
+
+fn require(ok: bool, field: &str, message: &str) -> Result<(), ScenarioError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(invalid(field, message))
+    }
+}
+
+fn index(field: &str, i: usize) -> String {
+    format!("{field}[{i}]")
+}
+
+fn parse_pubkeys(field: &str, pubkeys: &[String]) -> Result<Vec<Pubkey>, ScenarioError> {
+    pubkeys
+        .iter()
+        .enumerate()
+        .map(|(i, pubkey)| {
+            pubkey.parse().map_err(|_| {
+                invalid(
+                    index(field, i),
+                    format!("{pubkey:?} is not a base58 pubkey"),
+                )
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+use crate::rate_limit::MIN_RATE;
+
+/// Intended send times of an open-loop run, spaced evenly at a fixed rate.
+///
+/// Latency is measured from a request's intended send time rather than from
+/// when a thread or task got around to sending it, so a stalled client shows
+/// up as queueing delay instead of being hidden (coordinated omission).
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    start: Instant,
+    interval: Duration,
+}
+
+impl Schedule {
+    /// Starts a schedule now issuing `requests_per_second`
+    ///
+    /// Panics if `requests_per_second` is below [`MIN_RATE`]
+    pub fn starting_now(requests_per_second: f64) -> Schedule {
+        assert!(
+            requests_per_second >= MIN_RATE,
+            "rate {requests_per_second} is below {MIN_RATE}"
+        );
+        Schedule {
+            start: Instant::now(),
+            interval: Duration::from_secs_f64(1.0 / requests_per_second),
+        }
+    }
+
+    /// When request number `index` should be sent
+    pub fn intended(&self, index: u64) -> Instant {
+        self.start + self.interval.mul_f64(index as f64)
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use hdrhistogram::Histogram;
+use serde::Serialize;
+
+use crate::{outcome::Outcome, taxonomy::ErrorKey};
+
+/// Largest latency the histogram tracks exactly; slower requests saturate to it
+const MAX_TRACKABLE_MICROS: u64 = 60_000_000;
+
+/// Significant figures kept by the histogram
+const SIGFIGS: u8 = 3;
+
+/// Two-sided 95% critical values of Student's t distribution for 1 to 30
+/// degrees of freedom; larger samples use the normal approximation
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Normal approximation of [`T_95`] beyond 30 degrees of freedom
+const Z_95: f64 = 1.960;
+
+/// Timing and outcome of one request, including any retries
+pub struct Sample {
+    /// Time from the first attempt until the final outcome
+    pub latency: Duration,
+    /// Latency of the first attempt alone
+    pub first_try: Duration,
+    pub retries: u32,
+    pub outcome: Outcome,
+    /// Whether `outcome` matched the run's expectation
+    pub expected: bool,
+    /// What went wrong, if anything
+    pub error: Option<ErrorKey>,
+}
+
+/// Thread-safe collector of per-request latencies, in micros, and outcomes
+pub struct LatencyRecorder {
+    recorded: Mutex<Recorded>,
+    retries: AtomicU64,
+    retried: AtomicU64,
+    unexpected: AtomicU64,
+}
+
+struct Recorded {
+    latencies: Histogram<u64>,
+    first_try: Histogram<u64>,
+    outcomes: BTreeMap<Outcome, u64>,
+    errors: BTreeMap<ErrorKey, u64>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> LatencyRecorder {
+        LatencyRecorder {
+            recorded: Mutex::new(Recorded {
+                latencies: new_histogram(),
+                first_try: new_histogram(),
+                outcomes: BTreeMap::new(),
+                errors: BTreeMap::new(),
+            }),
+            retries: AtomicU64::new(0),
+            retried: AtomicU64::new(0),
+            unexpected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, sample: Sample) {
+        if sample.retries > 0 {
+            self.retries
+                .fetch_add(u64::from(sample.retries), Ordering::Relaxed);
+            self.retried.fetch_add(1, Ordering::Relaxed);
+        }
+        if !sample.expected {
+            self.unexpected.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut recorded = self.recorded.lock().unwrap();
+        recorded.latencies.saturating_record(micros(sample.latency));
+        recorded
+            .first_try
+            .saturating_record(micros(sample.first_try));
+        *recorded.outcomes.entry(sample.outcome).or_default() += 1;
+        if let Some(error) = sample.error {
+            *recorded.errors.entry(error).or_default() += 1;
+        }
+    }
+
+    pub fn into_stats(self, elapsed: Duration) -> RunStats {
+        let recorded = self.recorded.into_inner().unwrap();
+        RunStats {
+            elapsed,
+            latencies: recorded.latencies,
+            first_try: recorded.first_try,
+            errors: recorded
+                .outcomes
+                .iter()
+                .filter(|(outcome, _)| !outcome.is_response())
+                .map(|(_, count)| count)
+                .sum(),
+            outcomes: recorded.outcomes,
+            error_breakdown: recorded.errors,
+            unexpected: self.unexpected.into_inner(),
+            retries: self.retries.into_inner(),
+            retried: self.retried.into_inner(),
+        }
+    }
+}
+
+impl Default for LatencyRecorder {
+    fn default() -> LatencyRecorder {
+        LatencyRecorder::new()
+    }
+}
+
+/// Wall clock time, per-request latencies and outcomes of one benchmarked mode
+pub struct RunStats {
+    pub elapsed: Duration,
+    /// End to end latencies, including retries and their backoff
+    pub latencies: Histogram<u64>,
+    /// Latencies of each request's first attempt
+    pub first_try: Histogram<u64>,
+    /// Requests the node didn't answer with a result
+    pub errors: u64,
+    pub outcomes: BTreeMap<Outcome, u64>,
+    /// Request count by failure, including simulations that ran but failed
+    pub error_breakdown: BTreeMap<ErrorKey, u64>,
+    /// Requests whose outcome didn't match the run's expectation
+    pub unexpected: u64,
+    /// Total retry attempts across all requests
+    pub retries: u64,
+    /// Requests that needed at least one retry
+    pub retried: u64,
+}
+
+impl RunStats {
+    /// Adds the requests of another iteration of the same mode
+    pub fn absorb(&mut self, other: RunStats) {
+        self.elapsed += other.elapsed;
+        self.latencies
+            .add(&other.latencies)
+            .expect("histograms share bounds");
+        self.first_try
+            .add(&other.first_try)
+            .expect("histograms share bounds");
+        self.errors += other.errors;
+        for (outcome, count) in other.outcomes {
+            *self.outcomes.entry(outcome).or_default() += count;
+        }
+        for (error, count) in other.error_breakdown {
+            *self.error_breakdown.entry(error).or_default() += count;
+        }
+        self.unexpected += other.unexpected;
+        self.retries += other.retries;
+        self.retried += other.retried;
+    }
+
+    /// Completed requests per second of wall clock time
+    pub fn throughput(&self) -> f64 {
+        self.latencies.len() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Fraction of requests the node didn't answer with a result
+    pub fn error_rate(&self) -> f64 {
+        match self.latencies.len() {
+            0 => 0.0,
+            requests => self.errors as f64 / requests as f64,
+        }
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary::from_histogram(&self.latencies)
+    }
+}
+
+/// Latency percentiles in micros
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean: f64,
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+impl LatencySummary {
+    pub fn from_histogram(histogram: &Histogram<u64>) -> LatencySummary {
+        LatencySummary {
+            count: histogram.len(),
+            mean: histogram.mean(),
+            min: histogram.min(),
+            p50: histogram.value_at_quantile(0.5),
+            p90: histogram.value_at_quantile(0.9),
+            p99: histogram.value_at_quantile(0.99),
+            p999: histogram.value_at_quantile(0.999),
+            max: histogram.max(),
+        }
+    }
+}
+
+/// Headline numbers of one iteration of a mode
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Iteration {
+    pub total_micros: u64,
+    pub throughput: f64,
+    pub latency_micros: LatencySummary,
+}
+
+impl Iteration {
+    pub fn of(stats: &RunStats) -> Iteration {
+        Iteration {
+            total_micros: micros(stats.elapsed),
+            throughput: stats.throughput(),
+            latency_micros: stats.summary(),
+        }
+    }
+}
+
+/// How much repeated iterations of a mode varied
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct IterationSummary {
+    pub total_micros: Spread,
+    pub throughput: Spread,
+    pub p50_micros: Spread,
+    pub p90_micros: Spread,
+    pub p99_micros: Spread,
+}
+
+impl IterationSummary {
+    /// Summarizes `iterations`, if there are at least two to compare
+    pub fn of(iterations: &[Iteration]) -> Option<IterationSummary> {
+        if iterations.len() < 2 {
+            return None;
+        }
+        let spread = |value: fn(&Iteration) -> f64| {
+            Spread::of(&iterations.iter().map(value).collect::<Vec<_>>())
+        };
+        Some(IterationSummary {
+            total_micros: spread(|i| i.total_micros as f64),
+            throughput: spread(|i| i.throughput),
+            p50_micros: spread(|i| i.latency_micros.p50 as f64),
+            p90_micros: spread(|i| i.latency_micros.p90 as f64),
+            p99_micros: spread(|i| i.latency_micros.p99 as f64),
+        })
+    }
+}
+
+/// Mean, sample standard deviation and 95% confidence interval of the mean
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Spread {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl Spread {
+    /// Spread of at least two `values`
+    pub fn of(values: &[f64]) -> Spread {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        let t = T_95.get(values.len() - 2).copied().unwrap_or(Z_95);
+        let margin = t * std_dev / n.sqrt();
+        Spread {
+            mean,
+            std_dev,
+            ci_low: mean - margin,
+            ci_high: mean + margin,
+        }
+    }
+}
+
+/// Histogram value for `latency`, clamped to the trackable range
+pub fn micros(latency: Duration) -> u64 {
+    u64::try_from(latency.as_micros())
+        .unwrap_or(u64::MAX)
+        .max(1)
+}
+
+pub fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_TRACKABLE_MICROS, SIGFIGS).expect("valid histogram bounds")
+}
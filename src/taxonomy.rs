@@ -0,0 +1,75 @@
+use serde::Serialize;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+
+use crate::operation::CallResult;
+
+/// Longest message kept per error, so one-off details don't flood the report
+const MAX_MESSAGE_LEN: usize = 160;
+
+/// Identifies a class of failure: where it came from, its code and message
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct ErrorKey {
+    pub kind: &'static str,
+    pub code: Option<i64>,
+    pub message: String,
+}
+
+impl ErrorKey {
+    fn new(kind: &'static str, code: Option<i64>, message: impl ToString) -> ErrorKey {
+        let mut message = message.to_string();
+        if message.len() > MAX_MESSAGE_LEN {
+            let end = (0..=MAX_MESSAGE_LEN)
+                .rev()
+                .find(|&i| message.is_char_boundary(i))
+                .unwrap_or(0);
+            message.truncate(end);
+            message.push_str("...");
+        }
+        ErrorKey {
+            kind,
+            code,
+            message,
+        }
+    }
+
+    pub fn of_error(err: &ClientError) -> ErrorKey {
+        match err.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+                ErrorKey::new("rpc-response", Some(*code), message)
+            }
+            ClientErrorKind::RpcError(RpcError::RpcRequestError(message)) => {
+                ErrorKey::new("rpc-request", None, message)
+            }
+            ClientErrorKind::RpcError(RpcError::ParseError(message)) => {
+                ErrorKey::new("rpc-parse", None, message)
+            }
+            ClientErrorKind::RpcError(RpcError::ForUser(message)) => {
+                ErrorKey::new("rpc-for-user", None, message)
+            }
+            ClientErrorKind::Reqwest(err) => ErrorKey::new(
+                "reqwest",
+                err.status().map(|status| i64::from(status.as_u16())),
+                err,
+            ),
+            ClientErrorKind::Io(err) => ErrorKey::new("io", None, err),
+            ClientErrorKind::SerdeJson(err) => ErrorKey::new("serde-json", None, err),
+            ClientErrorKind::SigningError(err) => ErrorKey::new("signing", None, err),
+            ClientErrorKind::TransactionError(err) => ErrorKey::new("transaction", None, err),
+            ClientErrorKind::Custom(message) => ErrorKey::new("custom", None, message),
+        }
+    }
+
+    /// Classifies a failed request, or a simulation the node ran but the
+    /// transaction failed, as `None` for a clean success
+    pub fn of_result(result: &CallResult) -> Option<ErrorKey> {
+        match result {
+            Ok(err) => err
+                .as_ref()
+                .map(|err| ErrorKey::new("simulation", None, err)),
+            Err(err) => Some(ErrorKey::of_error(err)),
+        }
+    }
+}
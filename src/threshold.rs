@@ -0,0 +1,94 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{
+    benchmark::ModeRun,
+    cli::{Mode, ThresholdArgs},
+};
+
+/// A mode that broke one of the run's thresholds
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub endpoint: String,
+    pub mode: Mode,
+    /// Flag naming the threshold, such as `max-p99-us`
+    pub threshold: &'static str,
+    pub limit: f64,
+    pub actual: f64,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Error rates are fractions, where one decimal would round most away
+        let precision = if self.threshold == "max-error-rate" {
+            4
+        } else {
+            1
+        };
+        write!(
+            f,
+            "{} is {}, got {:.*}",
+            self.threshold, self.limit, precision, self.actual
+        )
+    }
+}
+
+/// Checks every mode's combined stats against `thresholds`
+pub fn check(thresholds: &ThresholdArgs, runs: &[ModeRun]) -> Vec<Violation> {
+    let float = |limit: Option<u64>| limit.map(|limit| limit as f64);
+    let mut violations = vec![];
+    for run in runs {
+        let summary = run.stats.summary();
+        // Each threshold with its limit, the run's value and whether the
+        // limit is a maximum rather than a minimum
+        let checks = [
+            (
+                "max-p50-us",
+                float(thresholds.max_p50_us),
+                summary.p50 as f64,
+                true,
+            ),
+            (
+                "max-p90-us",
+                float(thresholds.max_p90_us),
+                summary.p90 as f64,
+                true,
+            ),
+            (
+                "max-p99-us",
+                float(thresholds.max_p99_us),
+                summary.p99 as f64,
+                true,
+            ),
+            (
+                "max-error-rate",
+                thresholds.max_error_rate,
+                run.stats.error_rate(),
+                true,
+            ),
+            (
+                "max-unexpected",
+                float(thresholds.max_unexpected),
+                run.stats.unexpected as f64,
+                true,
+            ),
+            ("min-rps", thresholds.min_rps, run.stats.throughput(), false),
+        ];
+        for (threshold, limit, actual, maximum) in checks {
+            let Some(limit) = limit else {
+                continue;
+            };
+            if (maximum && actual > limit) || (!maximum && actual < limit) {
+                violations.push(Violation {
+                    endpoint: run.endpoint.clone(),
+                    mode: run.mode,
+                    threshold,
+                    limit,
+                    actual,
+                });
+            }
+        }
+    }
+    violations
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread::{self, ThreadId},
+    time::Instant,
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    subscriber::Interest,
+    Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+/// Process id every event is reported under
+const PID: u64 = 1;
+
+/// Track ids of task lanes start here, clear of thread track ids
+const FIRST_LANE_TID: u64 = 1_000_000;
+
+/// Records every closed span of this crate as a Chrome trace event, to open
+/// in Perfetto or `chrome://tracing`.
+///
+/// Spans go on the track of the thread they're created on, like the rayon
+/// pool's. Root spans created inside a tokio runtime, like the async runners'
+/// requests, instead get a lane of their own while they're open, since their
+/// task may hop between worker threads. Their child spans share the lane, so
+/// overlapping requests show up side by side.
+pub struct ChromeLayer {
+    started: Instant,
+    trace: Arc<Mutex<Trace>>,
+}
+
+/// The events a [`ChromeLayer`] recorded so far
+#[derive(Clone)]
+pub struct ChromeTrace {
+    trace: Arc<Mutex<Trace>>,
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<Event>,
+    threads: HashMap<ThreadId, u64>,
+    /// Whether each lane has an open span
+    lanes: Vec<bool>,
+}
+
+/// A trace event, in the Chrome trace event format
+#[derive(Serialize)]
+struct Event {
+    name: String,
+    ph: &'static str,
+    /// Micros since the layer was created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u64,
+    tid: u64,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    args: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+    Thread(u64),
+    Lane(usize),
+}
+
+impl Track {
+    fn tid(self) -> u64 {
+        match self {
+            Track::Thread(tid) => tid,
+            Track::Lane(lane) => FIRST_LANE_TID + lane as u64,
+        }
+    }
+}
+
+/// Kept in each open span's extensions
+struct SpanTiming {
+    started: Instant,
+    track: Track,
+    /// Whether the span took its lane, and frees it when closed
+    owns_lane: bool,
+    args: Map<String, Value>,
+}
+
+impl ChromeLayer {
+    pub fn new() -> (ChromeLayer, ChromeTrace) {
+        let trace = Arc::new(Mutex::new(Trace::default()));
+        let layer = ChromeLayer {
+            started: Instant::now(),
+            trace: Arc::clone(&trace),
+        };
+        (layer, ChromeTrace { trace })
+    }
+
+    /// Installs the layer as the global subscriber, so every thread's spans
+    /// are recorded. Fails if another subscriber is installed already.
+    pub fn install() -> io::Result<ChromeTrace> {
+        let (layer, trace) = ChromeLayer::new();
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+            .map_err(io::Error::other)?;
+        Ok(trace)
+    }
+}
+
+impl Trace {
+    fn thread_track(&mut self) -> Track {
+        let thread = thread::current();
+        let next = self.threads.len() as u64 + 1;
+        let tid = *self.threads.entry(thread.id()).or_insert_with(|| {
+            let name = thread
+                .name()
+                .map_or_else(|| format!("thread {next}"), |name| name.to_string());
+            self.events.push(Event::thread_name(next, name));
+            next
+        });
+        Track::Thread(tid)
+    }
+
+    fn take_lane(&mut self) -> Track {
+        let lane = match self.lanes.iter().position(|busy| !busy) {
+            Some(lane) => lane,
+            None => {
+                self.lanes.push(false);
+                let lane = self.lanes.len() - 1;
+                let name = format!("task lane {lane}");
+                self.events
+                    .push(Event::thread_name(Track::Lane(lane).tid(), name));
+                lane
+            }
+        };
+        self.lanes[lane] = true;
+        Track::Lane(lane)
+    }
+}
+
+impl Event {
+    /// Metadata naming a track
+    fn thread_name(tid: u64, name: String) -> Event {
+        Event {
+            name: "thread_name".to_string(),
+            ph: "M",
+            ts: None,
+            dur: None,
+            pid: PID,
+            tid,
+            args: Map::from_iter([("name".to_string(), Value::String(name))]),
+        }
+    }
+}
+
+impl ChromeTrace {
+    /// Number of spans recorded so far
+    pub fn spans(&self) -> usize {
+        let trace = self.trace.lock().unwrap();
+        trace.events.iter().filter(|event| event.ph == "X").count()
+    }
+
+    /// Writes the events recorded so far as a Chrome trace JSON file
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let trace = self.trace.lock().unwrap();
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(
+            &mut writer,
+            &serde_json::json!({
+                "traceEvents": trace.events,
+                "displayTimeUnit": "ms",
+            }),
+        )?;
+        writer.flush()
+    }
+}
+
+impl<S> Layer<S> for ChromeLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    /// Leaves out events, and the spans of dependencies like hyper
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if metadata.is_span() && metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span is open");
+        let parent = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            extensions.get::<SpanTiming>().map(|timing| timing.track)
+        });
+        let mut trace = self.trace.lock().unwrap();
+        let (track, owns_lane) = match parent {
+            Some(track @ Track::Lane(_)) => (track, false),
+            // Like the blocking rpc client's requests, run on the calling
+            // thread by its own runtime
+            Some(Track::Thread(_)) => (trace.thread_track(), false),
+            None if tokio::runtime::Handle::try_current().is_ok() => (trace.take_lane(), true),
+            None => (trace.thread_track(), false),
+        };
+        drop(trace);
+
+        let mut args = Map::new();
+        attrs.record(&mut ArgsVisitor(&mut args));
+        span.extensions_mut().insert(SpanTiming {
+            started: Instant::now(),
+            track,
+            owns_lane,
+            args,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span is open");
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            values.record(&mut ArgsVisitor(&mut timing.args));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("span is open");
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+        let micros = |instant: Instant| instant.duration_since(self.started).as_secs_f64() * 1e6;
+        let event = Event {
+            name: span.name().to_string(),
+            ph: "X",
+            ts: Some(micros(timing.started)),
+            dur: Some(timing.started.elapsed().as_secs_f64() * 1e6),
+            pid: PID,
+            tid: timing.track.tid(),
+            args: timing.args,
+        };
+        let mut trace = self.trace.lock().unwrap();
+        trace.events.push(event);
+        if let (true, Track::Lane(lane)) = (timing.owns_lane, timing.track) {
+            trace.lanes[lane] = false;
+        }
+    }
+}
+
+/// Collects span fields as event args
+struct ArgsVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for ArgsVisitor<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
@@ -0,0 +1,141 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+/// SPL memo program
+const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// Lamports moved by each transfer, the rent-exempt minimum of an account
+/// without data, so transfers to fresh accounts pass rent checks
+const TRANSFER_LAMPORTS: u64 = 890_880;
+
+/// Compute unit limit requested by compute budget workloads
+const COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+/// Priority fee requested by compute budget workloads, in micro-lamports per unit
+const COMPUTE_UNIT_PRICE: u64 = 1;
+
+/// Builds the transactions a benchmark simulates
+pub trait Workload: Send + Sync {
+    fn build(&self) -> Transaction;
+
+    /// Whether the rpc rejects these transactions before simulating them
+    fn rejected(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkloadKind {
+    /// Unsigned tx with one accountless instruction, which fails to sanitize
+    Empty,
+    /// Signed SOL transfer to a fresh account
+    Transfer,
+    /// Signed SPL memo
+    Memo,
+    /// Compute unit limit and price followed by a transfer
+    ComputeBudget,
+    /// Several transfers to fresh accounts in one tx
+    Multi,
+}
+
+impl WorkloadKind {
+    /// Whether transactions of this kind carry a recent blockhash
+    pub fn needs_blockhash(self) -> bool {
+        self != WorkloadKind::Empty
+    }
+
+    /// Builds the generator for this kind. `payer` signs every transaction
+    /// and `instructions` sets the transfer count of [`WorkloadKind::Multi`].
+    pub fn into_workload(
+        self,
+        payer: Keypair,
+        instructions: u64,
+        blockhash: Hash,
+    ) -> Box<dyn Workload> {
+        match self {
+            WorkloadKind::Empty => Box::new(Empty),
+            kind => Box::new(Signed {
+                kind,
+                payer,
+                instructions,
+                blockhash,
+            }),
+        }
+    }
+}
+
+/// The original benchmark transaction, only exercising the rejection path
+pub struct Empty;
+
+impl Workload for Empty {
+    fn build(&self) -> Transaction {
+        Transaction::new_unsigned(Message::new(
+            &[Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![],
+            )],
+            None,
+        ))
+    }
+
+    fn rejected(&self) -> bool {
+        true
+    }
+}
+
+/// Transactions signed by a fee payer, which pass sanitization and are simulated
+pub struct Signed {
+    kind: WorkloadKind,
+    payer: Keypair,
+    instructions: u64,
+    blockhash: Hash,
+}
+
+impl Signed {
+    fn transfer(&self) -> Instruction {
+        system_instruction::transfer(
+            &self.payer.pubkey(),
+            &Pubkey::new_unique(),
+            TRANSFER_LAMPORTS,
+        )
+    }
+}
+
+impl Workload for Signed {
+    fn build(&self) -> Transaction {
+        let instructions = match self.kind {
+            WorkloadKind::Empty => unreachable!("empty workload is unsigned"),
+            WorkloadKind::Transfer => vec![self.transfer()],
+            WorkloadKind::Memo => vec![Instruction::new_with_bytes(
+                MEMO_PROGRAM_ID,
+                Pubkey::new_unique().to_string().as_bytes(),
+                vec![AccountMeta::new_readonly(self.payer.pubkey(), true)],
+            )],
+            WorkloadKind::ComputeBudget => vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNIT_LIMIT),
+                ComputeBudgetInstruction::set_compute_unit_price(COMPUTE_UNIT_PRICE),
+                self.transfer(),
+            ],
+            WorkloadKind::Multi => (0..self.instructions).map(|_| self.transfer()).collect(),
+        };
+        Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            self.blockhash,
+        )
+    }
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "banks")]
+
+mod common;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use common::mock;
+use solana_sdk::{hash::Hash, signature::Keypair};
+use tx_sim::{
+    outcome::Outcome, rpc_error::INVALID_PARAMS, workload::WorkloadKind, Benchmark, Mode,
+};
+
+#[test]
+fn bank_simulates_signed_workloads() {
+    let server = mock();
+    for kind in [
+        WorkloadKind::Transfer,
+        WorkloadKind::Memo,
+        WorkloadKind::ComputeBudget,
+        WorkloadKind::Multi,
+    ] {
+        // Built against a blockhash the bank never saw, with an unfunded payer
+        let workload = kind.into_workload(Keypair::new(), 3, Hash::new_unique());
+        let runs = Benchmark::builder()
+            .endpoint(server.url())
+            .mode(Mode::Banks)
+            .workload(Arc::from(workload))
+            .requests(8)
+            .run()
+            .unwrap();
+        assert_eq!(
+            runs[0].stats.outcomes,
+            BTreeMap::from([(Outcome::Success, 8)]),
+            "{kind:?}"
+        );
+    }
+}
+
+#[test]
+fn bank_rejects_the_empty_workload_like_the_rpc() {
+    let server = mock();
+    let runs = Benchmark::builder()
+        .endpoint(server.url())
+        .modes([Mode::Banks, Mode::Raw])
+        .requests(4)
+        .run()
+        .unwrap();
+    for run in &runs {
+        assert_eq!(
+            run.stats.outcomes,
+            BTreeMap::from([(Outcome::RpcError(INVALID_PARAMS), 4)]),
+            "{:?}",
+            run.mode
+        );
+        assert_eq!(run.stats.unexpected, 0);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{io, path::Path, process::Command};
+
+use tx_sim::baseline::{mann_whitney_u, median, normal_cdf, Baseline, Metric, Verdict};
+
+/// One of each value
+fn each(values: impl IntoIterator<Item = u64>) -> Vec<(f64, u64)> {
+    values.into_iter().map(|value| (value as f64, 1)).collect()
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{actual} isn't within {tolerance} of {expected}"
+    );
+}
+
+#[test]
+fn normal_cdf_matches_known_values() {
+    assert_close(normal_cdf(0.0), 0.5, 1e-6);
+    assert_close(normal_cdf(1.96), 0.975_002_1, 1e-6);
+    assert_close(normal_cdf(-1.0), 0.158_655_3, 1e-6);
+}
+
+#[test]
+fn median_weighs_samples_by_count() {
+    assert_eq!(median(&each([3, 1, 2])), 2.0);
+    // The lower of the two middle values
+    assert_eq!(median(&each([1, 2])), 1.0);
+    assert_eq!(median(&[(10.0, 1), (1.0, 3)]), 1.0);
+    assert!(median(&[]).is_nan());
+}
+
+#[test]
+fn identical_samples_show_no_difference() {
+    let test = mann_whitney_u(&[(5.0, 10)], &[(5.0, 10)]).unwrap();
+    assert_eq!((test.z, test.p_value), (0.0, 1.0));
+
+    let test = mann_whitney_u(&each(1..=10), &each(1..=10)).unwrap();
+    assert_eq!(test.z, 0.0);
+    assert_close(test.p_value, 1.0, 1e-6);
+}
+
+#[test]
+fn clearly_shifted_samples_are_significant() {
+    // U = 0, the normal approximation with continuity correction giving
+    // z = -3.7418 and p = 0.00018267
+    let test = mann_whitney_u(&each(1..=10), &each(11..=20)).unwrap();
+    assert_close(test.z, -3.741_848, 1e-5);
+    assert_close(test.p_value, 0.000_182_67, 1e-7);
+
+    let reversed = mann_whitney_u(&each(11..=20), &each(1..=10)).unwrap();
+    assert_close(reversed.z, -test.z, 1e-12);
+    assert_close(reversed.p_value, test.p_value, 1e-12);
+}
+
+#[test]
+fn heavily_tied_samples_use_the_tie_correction() {
+    // Two values only: ranks 5.5 and 15.5, U = 20, variance 131.58 after
+    // the tie correction
+    let test = mann_whitney_u(&[(1.0, 8), (2.0, 2)], &[(1.0, 2), (2.0, 8)]).unwrap();
+    assert_close(test.z, -2.571_750, 1e-5);
+    assert_close(test.p_value, 0.010_118_6, 1e-6);
+}
+
+#[test]
+fn too_few_samples_are_untested() {
+    assert!(mann_whitney_u(&[(1.0, 1)], &each(1..=10)).is_none());
+    let metric = Metric::higher_is_faster("req/s", &[100.0], &[200.0, 300.0]);
+    assert_eq!(metric.verdict, Verdict::Untested);
+    assert_eq!(metric.p_value, None);
+}
+
+#[test]
+fn verdicts_follow_the_metric_direction() {
+    let low = (1..=10).map(|micros| (micros, 1)).collect::<Vec<_>>();
+    let high = (11..=20).map(|micros| (micros, 1)).collect::<Vec<_>>();
+    let verdict = |then, now| Metric::lower_is_faster("latency p50", then, now).verdict;
+    assert_eq!(verdict(&high, &low), Verdict::Faster);
+    assert_eq!(verdict(&low, &high), Verdict::Slower);
+    assert_eq!(verdict(&low, &low), Verdict::NoChange);
+
+    let low = (1..=10).map(f64::from).collect::<Vec<_>>();
+    let high = (11..=20).map(f64::from).collect::<Vec<_>>();
+    let metric = Metric::higher_is_faster("req/s", &low, &high);
+    assert_eq!(metric.verdict, Verdict::Faster);
+    assert_eq!(metric.baseline, 5.0);
+    assert_eq!(metric.current, 15.0);
+    assert_close(metric.change, 2.0, 1e-12);
+    assert_eq!(
+        Metric::higher_is_faster("req/s", &high, &low).verdict,
+        Verdict::Slower
+    );
+}
+
+#[test]
+fn baselines_are_named_by_file_name_only() {
+    let err = Baseline::load(Path::new("baselines"), "../secret").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = Baseline::new("a/b")
+        .save(Path::new("baselines"))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn missing_baseline_fails_before_the_run() {
+    let dir = std::env::temp_dir().join(format!("tx_sim-{}-baselines", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_tx_sim"))
+        .args(["bench", "--mock", "--baseline", "missing", "--baseline-dir"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("error: failed to load baseline \"missing\""),
+        "{stderr}"
+    );
+    assert!(output.stdout.is_empty(), "ran anyway");
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use std::{collections::BTreeMap, io, sync::Arc, time::Duration};
+
+use common::{mock, MODES};
+use solana_sdk::{hash::Hash, signature::Keypair};
+use tx_sim::{
+    cli::{BenchArgs, ConcurrencyArgs},
+    operation::Method,
+    outcome::{Expect, Outcome},
+    rpc_error::INVALID_PARAMS,
+    workload::WorkloadKind,
+    Benchmark, Mode,
+};
+
+#[test]
+fn empty_workload_is_rejected_in_every_mode() {
+    let server = mock();
+    let runs = Benchmark::builder()
+        .endpoint(server.url())
+        .modes(MODES)
+        .requests(20)
+        .batch_size(8)
+        .run()
+        .unwrap();
+
+    assert_eq!(
+        runs.iter().map(|run| run.mode).collect::<Vec<_>>(),
+        MODES.to_vec()
+    );
+    for run in &runs {
+        assert_eq!(run.endpoint, server.url());
+        assert_eq!(run.stats.latencies.len(), 20, "{:?}", run.mode);
+        assert_eq!(
+            run.stats.outcomes,
+            BTreeMap::from([(Outcome::RpcError(INVALID_PARAMS), 20)]),
+            "{:?}",
+            run.mode
+        );
+        assert_eq!(run.stats.errors, 20);
+        assert_eq!(run.stats.unexpected, 0);
+    }
+}
+
+#[test]
+fn methods_succeed_against_the_mock() {
+    let server = mock();
+    for method in [
+        Method::GetLatestBlockhash,
+        Method::GetAccountInfo,
+        Method::GetMultipleAccounts,
+        Method::GetBalance,
+        Method::GetSlot,
+        Method::GetFeeForMessage,
+    ] {
+        let runs = Benchmark::builder()
+            .endpoint(server.url())
+            .modes(MODES)
+            .method(method)
+            .expect(Expect::Success)
+            .requests(8)
+            .run()
+            .unwrap();
+        for run in &runs {
+            assert_eq!(
+                run.stats.outcomes,
+                BTreeMap::from([(Outcome::Success, 8)]),
+                "{method} {:?}",
+                run.mode
+            );
+            assert_eq!(run.stats.unexpected, 0);
+        }
+    }
+}
+
+#[test]
+fn signed_workloads_succeed_in_every_mode() {
+    let server = mock();
+    for kind in [
+        WorkloadKind::Transfer,
+        WorkloadKind::Memo,
+        WorkloadKind::ComputeBudget,
+        WorkloadKind::Multi,
+    ] {
+        let workload = kind.into_workload(Keypair::new(), 3, Hash::new_unique());
+        let runs = Benchmark::builder()
+            .endpoint(server.url())
+            .modes(MODES)
+            .workload(Arc::from(workload))
+            .expect(Expect::Success)
+            .requests(8)
+            .run()
+            .unwrap();
+        for run in &runs {
+            assert_eq!(
+                run.stats.outcomes,
+                BTreeMap::from([(Outcome::Success, 8)]),
+                "{kind:?} {:?}",
+                run.mode
+            );
+            assert_eq!(run.stats.unexpected, 0);
+        }
+    }
+}
+
+#[test]
+fn iterations_are_combined_and_warmup_is_not_measured() {
+    let server = mock();
+    let runs = Benchmark::builder()
+        .endpoint(server.url())
+        .method(Method::GetSlot)
+        .requests(10)
+        .warmup(5)
+        .iterations(3)
+        .run()
+        .unwrap();
+
+    assert_eq!(runs.len(), 2, "sync and async by default");
+    for run in &runs {
+        assert_eq!(run.iterations.len(), 3);
+        assert_eq!(run.stats.latencies.len(), 30);
+        for iteration in &run.iterations {
+            assert_eq!(iteration.latency_micros.count, 10);
+        }
+    }
+}
+
+#[test]
+fn endpoints_run_in_order() {
+    let (first, second) = (mock(), mock());
+    let runs = Benchmark::builder()
+        .endpoint(first.url())
+        .endpoint(second.url())
+        .mode(Mode::Raw)
+        .mode(Mode::Async)
+        .method(Method::GetSlot)
+        .requests(4)
+        .run()
+        .unwrap();
+
+    let order = runs
+        .iter()
+        .map(|run| (run.endpoint.clone(), run.mode))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        order,
+        [
+            (first.url(), Mode::Raw),
+            (first.url(), Mode::Async),
+            (second.url(), Mode::Raw),
+            (second.url(), Mode::Async),
+        ]
+    );
+}
+
+#[test]
+fn open_loop_runs_pace_requests() {
+    let server = mock();
+    let runs = Benchmark::builder()
+        .endpoint(server.url())
+        .mode(Mode::Async)
+        .method(Method::GetSlot)
+        .requests(10)
+        .arrival_rate(100.0)
+        .run()
+        .unwrap();
+
+    // The last request is due 90ms after the first
+    assert!(runs[0].stats.elapsed >= Duration::from_millis(90));
+    assert_eq!(runs[0].stats.latencies.len(), 10);
+}
+
+#[test]
+fn unreachable_endpoint_counts_transport_errors() {
+    // Start and stop a mock to find a port nothing listens on
+    let url = {
+        let server = mock();
+        server.url()
+    };
+    let runs = Benchmark::builder()
+        .endpoint(url)
+        .modes(MODES)
+        .method(Method::GetSlot)
+        .requests(4)
+        .timeout(Duration::from_secs(5))
+        .run()
+        .unwrap();
+
+    for run in &runs {
+        assert_eq!(
+            run.stats.outcomes,
+            BTreeMap::from([(Outcome::Transport, 4)]),
+            "{:?}",
+            run.mode
+        );
+        assert_eq!(run.stats.unexpected, 4);
+    }
+}
+
+#[test]
+fn builder_rejects_missing_endpoints() {
+    let err = Benchmark::builder().build().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn builder_rejects_settings_that_cant_run() {
+    let threads = |worker_threads, max_in_flight| ConcurrencyArgs {
+        worker_threads,
+        max_in_flight,
+        ..BenchArgs::default().concurrency
+    };
+    let builder = || Benchmark::builder().endpoint("http://127.0.0.1:8899");
+    for (setting, builder) in [
+        ("no worker threads", builder().concurrency(threads(0, None))),
+        (
+            "nothing in flight",
+            builder().concurrency(threads(1, Some(0))),
+        ),
+        ("zero rps", builder().rate_limit(0.0, 1)),
+        ("tiny rps", builder().rate_limit(1e-30, 1)),
+        ("nan arrival rate", builder().arrival_rate(f64::NAN)),
+        ("zero arrival rate", builder().arrival_rate(0.0)),
+        (
+            "no accounts",
+            builder().method(Method::GetBalance).addresses(vec![]),
+        ),
+    ] {
+        let err = builder.build().err().expect(setting);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{setting}");
+    }
+}
+
+#[test]
+fn builder_defaults_match_the_bench_flags() {
+    let server = mock();
+    let defaults = BenchArgs::default();
+    let benchmark = Benchmark::builder().endpoint(server.url()).build().unwrap();
+    assert_eq!(benchmark.requests(), defaults.requests);
+    let runs = benchmark.run();
+    assert_eq!(
+        runs.iter().map(|run| run.mode).collect::<Vec<_>>(),
+        defaults.modes
+    );
+}
+
+#[test]
+fn banks_mode_only_simulates() {
+    let err = Benchmark::builder()
+        .endpoint("http://127.0.0.1:8899")
+        .mode(Mode::Banks)
+        .method(Method::GetSlot)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,74 @@
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use tx_sim::cli::{Cli, Command};
+
+fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from([&["tx_sim"], args].concat())
+}
+
+#[test]
+fn cli_is_consistent() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn run_takes_a_replay() {
+    let cli = parse(&["run", "x.toml", "--replay", "calls.jsonl"]).unwrap();
+    let Command::Run(args) = cli.command else {
+        panic!("expected the run subcommand");
+    };
+    assert!(args.record.replay.is_some());
+}
+
+#[test]
+fn replay_conflicts_with_endpoints() {
+    for args in [
+        ["bench", "--replay", "calls.jsonl", "--url", "devnet"].as_slice(),
+        &["bench", "--replay", "calls.jsonl", "--mock"],
+        &["bench", "--replay", "calls.jsonl", "--record", "out.jsonl"],
+    ] {
+        let err = parse(args).expect_err("arguments conflict");
+        assert_eq!(err.kind(), ErrorKind::ArgumentConflict, "{args:?}");
+    }
+}
+
+#[test]
+fn counts_must_be_positive() {
+    for args in [
+        ["bench", "--requests", "0"].as_slice(),
+        &["bench", "--iterations", "0"],
+        &["bench", "--rayon-threads", "0"],
+        &["bench", "--worker-threads", "0"],
+        &["bench", "--timeout", "0"],
+    ] {
+        let err = parse(args).expect_err("zero is rejected");
+        assert_eq!(err.kind(), ErrorKind::ValueValidation, "{args:?}");
+    }
+}
+
+#[test]
+fn rates_have_a_floor() {
+    for args in [
+        ["bench", "--rps", "0"].as_slice(),
+        &["bench", "--rps", "1e-30"],
+        &["bench", "--rps", "inf"],
+        &["bench", "--arrival-rate", "1e-30"],
+    ] {
+        let err = parse(args).expect_err("rate is too low to schedule");
+        assert_eq!(err.kind(), ErrorKind::ValueValidation, "{args:?}");
+    }
+    assert!(parse(&["bench", "--rps", "0.001"]).is_ok());
+}
+
+#[test]
+fn baseline_names_stay_in_the_baseline_dir() {
+    for args in [
+        ["bench", "--baseline", "../elsewhere"].as_slice(),
+        &["bench", "--save-baseline", "nested/name"],
+        &["run", "x.toml", "--save-baseline", "/tmp/name"],
+        &["bench", "--baseline", ""],
+    ] {
+        let err = parse(args).expect_err("name isn't a plain file name");
+        assert_eq!(err.kind(), ErrorKind::ValueValidation, "{args:?}");
+    }
+    assert!(parse(&["bench", "--save-baseline", "v1.2-main"]).is_ok());
+}
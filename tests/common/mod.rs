@@ -0,0 +1,16 @@
+// Each test crate compiles its own copy and uses only some of these
+#![allow(dead_code)]
+
+use tx_sim::{cli::FaultArgs, fault::Faults, mock_server::MockServer, Mode};
+
+pub const MODES: [Mode; 4] = [Mode::Sync, Mode::Async, Mode::Raw, Mode::Batch];
+
+pub fn mock() -> MockServer {
+    MockServer::start(([127, 0, 0, 1], 0).into()).expect("failed to start mock rpc server")
+}
+
+pub fn mock_with_faults(args: &FaultArgs) -> MockServer {
+    let faults = Faults::new(args).unwrap();
+    MockServer::start_with_faults(([127, 0, 0, 1], 0).into(), faults)
+        .expect("failed to start mock rpc server")
+}
@@ -0,0 +1,205 @@
+mod common;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use common::mock_with_faults;
+use tx_sim::{
+    cli::FaultArgs,
+    fault::{Fault, Faults, Latency, MAX_DELAY},
+    mock_server::MockServer,
+    operation::Method,
+    outcome::Outcome,
+    rpc_error::{INVALID_PARAMS, NODE_UNHEALTHY},
+    Benchmark, Mode,
+};
+
+fn outcomes(server: &MockServer, modes: &[Mode]) -> Vec<(Mode, BTreeMap<Outcome, u64>)> {
+    Benchmark::builder()
+        .endpoint(server.url())
+        .modes(modes.iter().copied())
+        .method(Method::GetSlot)
+        .requests(8)
+        .batch_size(4)
+        .timeout(Duration::from_secs(5))
+        .run()
+        .unwrap()
+        .into_iter()
+        .map(|run| (run.mode, run.stats.outcomes))
+        .collect()
+}
+
+#[test]
+fn rpc_errors_answer_every_call() {
+    for (code, args) in [
+        (
+            INVALID_PARAMS,
+            FaultArgs {
+                fault_invalid_params: 1.0,
+                ..FaultArgs::default()
+            },
+        ),
+        (
+            NODE_UNHEALTHY,
+            FaultArgs {
+                fault_unhealthy: 1.0,
+                ..FaultArgs::default()
+            },
+        ),
+    ] {
+        let server = mock_with_faults(&args);
+        // The rpc clients ask for the node version first, to map the
+        // commitment, and report that failing as a request error instead
+        for (mode, outcomes) in outcomes(&server, &[Mode::Raw, Mode::Batch]) {
+            assert_eq!(
+                outcomes,
+                BTreeMap::from([(Outcome::RpcError(code), 8)]),
+                "{code} {mode:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn broken_responses_are_transport_errors() {
+    let all = [Mode::Sync, Mode::Async, Mode::Raw, Mode::Batch];
+    for (args, modes) in [
+        // The rpc clients' http sender retries 429s itself, backing off for
+        // seconds before giving up
+        (
+            FaultArgs {
+                fault_rate_limited: 1.0,
+                ..FaultArgs::default()
+            },
+            &[Mode::Raw, Mode::Batch][..],
+        ),
+        (
+            FaultArgs {
+                fault_unavailable: 1.0,
+                ..FaultArgs::default()
+            },
+            &all,
+        ),
+        (
+            FaultArgs {
+                fault_malformed: 1.0,
+                ..FaultArgs::default()
+            },
+            &all,
+        ),
+        (
+            FaultArgs {
+                fault_truncated: 1.0,
+                ..FaultArgs::default()
+            },
+            &all,
+        ),
+        (
+            FaultArgs {
+                fault_drop: 1.0,
+                ..FaultArgs::default()
+            },
+            &all,
+        ),
+    ] {
+        let server = mock_with_faults(&args);
+        let runs = outcomes(&server, modes);
+        assert_eq!(runs.len(), modes.len());
+        for (mode, outcomes) in runs {
+            assert_eq!(
+                outcomes,
+                BTreeMap::from([(Outcome::Transport, 8)]),
+                "{args:?} {mode:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn latency_delays_every_response() {
+    let server = mock_with_faults(&FaultArgs {
+        fault_latency: Some(Latency::Fixed(20.0)),
+        ..FaultArgs::default()
+    });
+    let runs = Benchmark::builder()
+        .endpoint(server.url())
+        .mode(Mode::Async)
+        .method(Method::GetSlot)
+        .requests(4)
+        .run()
+        .unwrap();
+    assert!(
+        runs[0].stats.summary().min >= 20_000,
+        "{:?}",
+        runs[0].stats.summary()
+    );
+}
+
+#[test]
+fn faults_hit_about_their_share() {
+    let faults = Faults::new(&FaultArgs {
+        fault_unhealthy: 0.25,
+        fault_drop: 0.5,
+        ..FaultArgs::default()
+    })
+    .unwrap();
+    let mut rng = rand::thread_rng();
+    let mut counts = HashMap::<_, u32>::new();
+    for _ in 0..10_000 {
+        *counts.entry(faults.roll(&mut rng).1).or_default() += 1;
+    }
+    for (fault, expected) in [
+        (None, 2_500),
+        (Some(Fault::Unhealthy), 2_500),
+        (Some(Fault::Drop), 5_000),
+    ] {
+        let count = counts[&fault];
+        assert!(count.abs_diff(expected) < 300, "{fault:?}: {count}");
+    }
+}
+
+#[test]
+fn latency_specs_round_trip() {
+    for spec in [
+        "fixed:5",
+        "uniform:1,3",
+        "normal:10,2",
+        "exponential:4",
+        "lognormal:5,0.5",
+    ] {
+        assert_eq!(spec.parse::<Latency>().unwrap().to_string(), spec);
+    }
+    for spec in [
+        "fixed",
+        "fixed:-1",
+        "fixed:1e300",
+        "uniform:3,1",
+        "gamma:1,2",
+        "normal:1",
+    ] {
+        assert!(spec.parse::<Latency>().is_err(), "{spec}");
+    }
+}
+
+#[test]
+fn latency_samples_are_capped() {
+    let mut rng = rand::thread_rng();
+    let wide = "lognormal:3600000,3600000".parse::<Latency>().unwrap();
+    for _ in 0..100 {
+        assert!(wide.sample(&mut rng) <= MAX_DELAY);
+    }
+    assert_eq!(Latency::Fixed(f64::INFINITY).sample(&mut rng), MAX_DELAY);
+}
+
+#[test]
+fn probabilities_over_one_are_rejected() {
+    let err = Faults::new(&FaultArgs {
+        fault_unavailable: 0.6,
+        fault_drop: 0.6,
+        ..FaultArgs::default()
+    })
+    .expect_err("probabilities add up to 1.2");
+    assert!(err.contains("more than 1"), "{err}");
+}
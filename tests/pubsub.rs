@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tx_sim::{
+    mock_pubsub::MockPubsubServer,
+    pubsub::{self, PubsubOptions, PubsubStats, Subscription},
+};
+
+/// Notification interval of the mock
+const INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long each subscription listens, about ten intervals
+const WINDOW: Duration = Duration::from_millis(500);
+
+fn options(subscription: Subscription, subscriptions: u32) -> PubsubOptions {
+    PubsubOptions {
+        subscription,
+        subscriptions,
+        window: WINDOW,
+        account: Pubkey::new_unique(),
+        signature: Signature::new_unique(),
+    }
+}
+
+fn run(subscription: Subscription, subscriptions: u32) -> PubsubStats {
+    let server = MockPubsubServer::start(([127, 0, 0, 1], 0).into(), INTERVAL)
+        .expect("failed to start mock pubsub server");
+    tokio::runtime::Runtime::new()
+        .expect("failed to build tokio runtime")
+        .block_on(pubsub::run(
+            &server.url(),
+            &options(subscription, subscriptions),
+        ))
+        .expect("failed to connect pubsub client")
+}
+
+#[test]
+fn subscriptions_are_notified_every_interval() {
+    for subscription in [Subscription::Slot, Subscription::Account] {
+        let stats = run(subscription, 4);
+        assert!(stats.failures.is_empty(), "{:?}", stats.failures);
+        assert_eq!(stats.subscribe.len(), 4, "{subscription}");
+        // 20 per second, give or take a tick at either end of the window
+        let rate = stats.rate();
+        assert!((16.0..=24.0).contains(&rate), "{subscription}: {rate}");
+        let interval = stats.intervals.value_at_quantile(0.5);
+        assert!(
+            (40_000..=60_000).contains(&interval),
+            "{subscription}: median interval {interval}us"
+        );
+    }
+}
+
+#[test]
+fn signature_subscriptions_are_notified_once() {
+    let stats = run(Subscription::Signature, 4);
+    assert!(stats.failures.is_empty(), "{:?}", stats.failures);
+    assert_eq!(stats.subscribe.len(), 4);
+    assert_eq!(stats.notifications, 4);
+    assert_eq!(stats.first_notification.len(), 4);
+    assert!(stats.intervals.is_empty());
+}
+
+#[test]
+fn unreachable_endpoints_fail_to_connect() {
+    let result = tokio::runtime::Runtime::new()
+        .expect("failed to build tokio runtime")
+        .block_on(pubsub::run(
+            "ws://127.0.0.1:1",
+            &options(Subscription::Slot, 1),
+        ));
+    assert!(result.is_err());
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use tx_sim::rate_limit::RateLimiter;
+
+#[test]
+fn burst_is_allowed_up_front_then_spaced_at_the_rate() {
+    let limiter = RateLimiter::new(10.0, 3);
+    for i in 0..3 {
+        assert_eq!(limiter.reserve(), Duration::ZERO, "request {i}");
+    }
+    // Reservations are taken back to back, so each wait falls just short of
+    // its slot at 100ms intervals
+    for slot in 1..=3 {
+        let wait = limiter.reserve();
+        let due = Duration::from_millis(100 * slot);
+        assert!(
+            wait <= due && wait > due - Duration::from_millis(20),
+            "request {}: waits {wait:?}",
+            slot + 2
+        );
+    }
+}
+
+#[test]
+fn without_a_burst_every_request_waits_its_turn() {
+    let limiter = RateLimiter::new(20.0, 1);
+    assert_eq!(limiter.reserve(), Duration::ZERO);
+    let wait = limiter.reserve();
+    assert!(
+        wait <= Duration::from_millis(50) && wait > Duration::from_millis(30),
+        "waits {wait:?}"
+    );
+}
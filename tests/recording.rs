@@ -0,0 +1,176 @@
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use common::{mock, mock_with_faults, MODES};
+use tx_sim::{
+    cli::FaultArgs,
+    fault::Latency,
+    mock_server::MockServer,
+    operation::Method,
+    outcome::Outcome,
+    recording::{Recorder, Recording, RecordingProxy, ReplayServer},
+    rpc_error::{INVALID_PARAMS, METHOD_NOT_FOUND},
+    scenario::Scenario,
+    Benchmark, BenchmarkBuilder, Mode,
+};
+
+fn recording_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tx_sim-{}-{name}.jsonl", std::process::id()))
+}
+
+/// Runs `method` in every mode through a recording proxy in front of
+/// `server`, returning the outcomes
+fn record(server: &MockServer, path: &Path, method: Method) -> Vec<BTreeMap<Outcome, u64>> {
+    let recorder = Arc::new(Recorder::create(path).unwrap());
+    let proxy = RecordingProxy::start(
+        ([127, 0, 0, 1], 0).into(),
+        server.url(),
+        Duration::from_secs(5),
+        recorder,
+    )
+    .unwrap();
+    outcomes(&proxy.url(), method)
+}
+
+fn outcomes(url: &str, method: Method) -> Vec<BTreeMap<Outcome, u64>> {
+    Benchmark::builder()
+        .endpoint(url)
+        .modes(MODES)
+        .method(method)
+        .requests(8)
+        .batch_size(4)
+        .run()
+        .unwrap()
+        .into_iter()
+        .map(|run| run.stats.outcomes)
+        .collect()
+}
+
+fn replay(path: &Path, latency: bool) -> ReplayServer {
+    let recording = Recording::load(path).unwrap();
+    ReplayServer::start(([127, 0, 0, 1], 0).into(), recording, latency).unwrap()
+}
+
+#[test]
+fn replay_answers_like_the_recorded_endpoint() {
+    let server = mock();
+    let path = recording_path("replay");
+    for method in [Method::SimulateTransaction, Method::GetAccountInfo] {
+        let recorded = record(&server, &path, method);
+        let replayed = outcomes(&replay(&path, false).url(), method);
+        assert_eq!(replayed, recorded, "{method}");
+    }
+    // The empty workload's error, for every call
+    let recorded = record(&server, &path, Method::SimulateTransaction);
+    for outcomes in recorded {
+        assert_eq!(
+            outcomes,
+            BTreeMap::from([(Outcome::RpcError(INVALID_PARAMS), 8)])
+        );
+    }
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn every_call_is_recorded() {
+    let server = mock();
+    let path = recording_path("calls");
+    record(&server, &path, Method::GetSlot);
+
+    let recording = Recording::load(&path).unwrap();
+    let slots = recording
+        .exchanges()
+        .iter()
+        .filter(|exchange| exchange.method == "getSlot")
+        .count();
+    // Batches are recorded call by call
+    assert_eq!(slots, 8 * MODES.len());
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn replay_reproduces_latency_if_asked() {
+    let server = mock_with_faults(&FaultArgs {
+        fault_latency: Some(Latency::Fixed(20.0)),
+        ..FaultArgs::default()
+    });
+    let path = recording_path("latency");
+    record(&server, &path, Method::GetSlot);
+
+    let min_latency = |latency| {
+        let server = replay(&path, latency);
+        let runs = Benchmark::builder()
+            .endpoint(server.url())
+            .mode(Mode::Raw)
+            .method(Method::GetSlot)
+            .requests(4)
+            .run()
+            .unwrap();
+        runs[0].stats.summary().min
+    };
+    assert!(min_latency(true) >= 20_000);
+    assert!(min_latency(false) < 20_000);
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn unrecorded_methods_are_not_found() {
+    let server = mock();
+    let path = recording_path("unrecorded");
+    record(&server, &path, Method::GetSlot);
+
+    let server = replay(&path, false);
+    let runs = Benchmark::builder()
+        .endpoint(server.url())
+        .modes([Mode::Raw, Mode::Batch])
+        .method(Method::GetBalance)
+        .requests(4)
+        .run()
+        .unwrap();
+    for run in &runs {
+        assert_eq!(
+            run.stats.outcomes,
+            BTreeMap::from([(Outcome::RpcError(METHOD_NOT_FOUND), 4)]),
+            "{:?}",
+            run.mode
+        );
+    }
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn bench_args_record_under_the_endpoint_and_replay() {
+    let server = mock();
+    let path = recording_path("args");
+    let mut args = Scenario::parse(
+        r#"
+        modes = ["async"]
+        requests = 4
+
+        [method]
+        name = "getSlot"
+        "#,
+        Path::new("scenarios"),
+    )
+    .unwrap();
+    args.urls = vec![server.url()];
+    args.record.record = Some(path.clone());
+    let runs = BenchmarkBuilder::from_args(&args).unwrap().run().unwrap();
+    assert_eq!(runs[0].endpoint, server.url());
+    assert_eq!(runs[0].stats.errors, 0);
+
+    args.record.record = None;
+    args.record.replay = Some(path.clone());
+    let benchmark = BenchmarkBuilder::from_args(&args).unwrap().build().unwrap();
+    assert_ne!(benchmark.endpoints(), [server.url()]);
+    drop(server);
+    let runs = benchmark.run();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(runs[0].stats.errors, 0);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use std::process::Command as Process;
+
+use clap::Parser;
+use common::{mock, MODES};
+use serde_json::Value;
+use tx_sim::{
+    cli::{Cli, Command},
+    report::Report,
+    rpc_error::INVALID_PARAMS,
+    BenchmarkBuilder,
+};
+
+#[test]
+fn report_records_config_environment_and_every_mode() {
+    let server = mock();
+    let cli = Cli::try_parse_from([
+        "tx_sim",
+        "bench",
+        "--url",
+        &server.url(),
+        "--requests",
+        "8",
+        "--mode",
+        "sync,async,raw,batch",
+    ])
+    .unwrap();
+    let Command::Bench(args) = cli.command else {
+        panic!("expected the bench subcommand");
+    };
+    let benchmark = BenchmarkBuilder::from_args(&args).unwrap().build().unwrap();
+    let mut report = Report::new(&args, &benchmark.expectation());
+    for run in benchmark.run() {
+        report.push(&run.endpoint, run.mode, &run.stats, &run.iterations);
+    }
+    let path = std::env::temp_dir().join(format!("tx_sim-{}-report.json", std::process::id()));
+    report.write(&path).unwrap();
+    let json: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(json["config"]["urls"][0], server.url());
+    assert_eq!(json["config"]["requests"], 8);
+    assert_eq!(
+        json["config"]["expect"],
+        format!("rpc-error {INVALID_PARAMS}")
+    );
+    assert_eq!(
+        json["environment"]["tx_sim_version"],
+        env!("CARGO_PKG_VERSION")
+    );
+    assert!(json["environment"]["cpus"].as_u64().unwrap() >= 1);
+
+    let modes = json["modes"].as_array().unwrap();
+    assert_eq!(modes.len(), MODES.len());
+    for (mode, report) in MODES.iter().zip(modes) {
+        assert_eq!(
+            report["mode"],
+            serde_json::to_value(mode).unwrap(),
+            "{mode:?}"
+        );
+        assert_eq!(report["requests"], 8, "{mode:?}");
+        let latency = &report["latency_micros"];
+        assert_eq!(latency["count"], 8, "{mode:?}");
+        let percentiles =
+            ["min", "p50", "p90", "p99", "p999", "max"].map(|p| latency[p].as_u64().unwrap());
+        assert!(
+            percentiles.windows(2).all(|w| w[0] <= w[1]),
+            "{mode:?}: {latency}"
+        );
+
+        assert_eq!(report["errors"], 8, "{mode:?}");
+        assert_eq!(report["error_rate"], 1.0, "{mode:?}");
+        assert_eq!(report["unexpected"], 0, "{mode:?}");
+        assert_eq!(
+            report["outcomes"][format!("rpc-error {INVALID_PARAMS}")],
+            8,
+            "{mode:?}"
+        );
+        let breakdown = report["error_breakdown"].as_array().unwrap();
+        assert_eq!(breakdown.len(), 1, "{mode:?}");
+        assert_eq!(breakdown[0]["code"], INVALID_PARAMS, "{mode:?}");
+        assert_eq!(breakdown[0]["count"], 8, "{mode:?}");
+    }
+}
+
+#[test]
+fn json_to_stdout_replaces_the_table() {
+    let output = Process::new(env!("CARGO_BIN_EXE_tx_sim"))
+        .args(["bench", "--mock", "--requests", "4", "--json", "-"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Nothing but the report, or it wouldn't parse
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["config"]["mock"], 1);
+    assert_eq!(json["modes"].as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::{io, time::Duration};
+
+use common::mock_with_faults;
+use solana_client::{
+    client_error::{reqwest, ClientError},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use tx_sim::{
+    cli::{FaultArgs, RetryArgs},
+    fault::Latency,
+    retry::{classify, RetryPolicy, Transient},
+    rpc_error::{INVALID_PARAMS, NODE_UNHEALTHY},
+};
+
+fn response_error(code: i64) -> ClientError {
+    RpcError::RpcResponseError {
+        code,
+        message: "error".to_string(),
+        data: RpcResponseErrorData::Empty,
+    }
+    .into()
+}
+
+/// The error a request to a mock with `faults` fails with
+fn http_error(faults: FaultArgs, timeout: Duration) -> ClientError {
+    let server = mock_with_faults(&faults);
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+        let result = client
+            .post(server.url())
+            .body(r#"{"jsonrpc":"2.0","id":1,"method":"getSlot"}"#)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        result.expect_err("request fails").into()
+    })
+}
+
+#[test]
+fn rpc_errors_are_transient_only_if_the_node_is_unhealthy() {
+    assert_eq!(
+        classify(&response_error(NODE_UNHEALTHY)),
+        Some(Transient::Unhealthy)
+    );
+    assert_eq!(classify(&response_error(INVALID_PARAMS)), None);
+
+    let version: ClientError =
+        RpcError::RpcRequestError("cluster version query failed: timed out".to_string()).into();
+    assert_eq!(classify(&version), Some(Transient::Transport));
+    let other: ClientError = RpcError::RpcRequestError("bad request".to_string()).into();
+    assert_eq!(classify(&other), None);
+}
+
+#[test]
+fn transport_failures_are_transient() {
+    let io: ClientError = io::Error::other("connection reset").into();
+    assert_eq!(classify(&io), Some(Transient::Transport));
+
+    let rate_limited = http_error(
+        FaultArgs {
+            fault_rate_limited: 1.0,
+            ..FaultArgs::default()
+        },
+        Duration::from_secs(5),
+    );
+    assert_eq!(classify(&rate_limited), Some(Transient::RateLimited));
+
+    let unavailable = http_error(
+        FaultArgs {
+            fault_unavailable: 1.0,
+            ..FaultArgs::default()
+        },
+        Duration::from_secs(5),
+    );
+    assert_eq!(classify(&unavailable), Some(Transient::Transport));
+
+    let dropped = http_error(
+        FaultArgs {
+            fault_drop: 1.0,
+            ..FaultArgs::default()
+        },
+        Duration::from_secs(5),
+    );
+    assert_eq!(classify(&dropped), Some(Transient::Transport));
+
+    let timed_out = http_error(
+        FaultArgs {
+            fault_latency: Some(Latency::Fixed(500.0)),
+            ..FaultArgs::default()
+        },
+        Duration::from_millis(50),
+    );
+    assert_eq!(classify(&timed_out), Some(Transient::Transport));
+}
+
+#[test]
+fn backoff_doubles_up_to_the_cap() {
+    let policy = RetryPolicy::new(&RetryArgs {
+        max_retries: 10,
+        backoff_ms: 100,
+        max_backoff_ms: 1_000,
+        retry_on: vec![Transient::Transport],
+    });
+    let backoffs = (0..6)
+        .map(|retry| policy.backoff(retry).as_millis())
+        .collect::<Vec<_>>();
+    assert_eq!(backoffs, [100, 200, 400, 800, 1_000, 1_000]);
+    // Doesn't overflow however many retries are allowed
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(1_000));
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use std::path::Path;
+
+use common::mock;
+use tx_sim::{
+    cli::Mode,
+    operation::Method,
+    scenario::{Scenario, ScenarioError},
+    threshold, BenchmarkBuilder,
+};
+
+fn parse(text: &str) -> Result<tx_sim::cli::BenchArgs, ScenarioError> {
+    Scenario::parse(text, Path::new("scenarios"))
+}
+
+fn error(text: &str) -> String {
+    parse(text).expect_err("scenario is invalid").to_string()
+}
+
+#[test]
+fn checked_in_scenarios_are_valid() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    for entry in dir.read_dir().unwrap() {
+        let path = entry.unwrap().path();
+        if let Err(err) = Scenario::load(&path) {
+            panic!("{}: {err}", path.display());
+        }
+    }
+}
+
+#[test]
+fn keys_override_defaults() {
+    let args = parse(
+        r#"
+        endpoints = ["devnet", "http://127.0.0.1:8899"]
+        modes = ["raw", "batch"]
+        requests = 100
+
+        [workload]
+        payer = "payer.json"
+
+        [method]
+        name = "getBalance"
+
+        [concurrency]
+        max-in-flight = 8
+
+        [thresholds]
+        max-p99-us = 20000
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        args.urls,
+        ["https://api.devnet.solana.com", "http://127.0.0.1:8899"]
+    );
+    assert_eq!(args.modes, [Mode::Raw, Mode::Batch]);
+    assert_eq!(args.requests, 100);
+    assert_eq!(args.method.method, Method::GetBalance);
+    assert_eq!(args.concurrency.max_in_flight, Some(8));
+    assert_eq!(args.thresholds.max_p99_us, Some(20000));
+    assert_eq!(
+        args.workload.payer.as_deref(),
+        Some(Path::new("scenarios/payer.json"))
+    );
+    // Left out, so the flag defaults apply
+    assert_eq!(args.batch_size, 8);
+    assert_eq!(args.timeout, 30);
+    assert_eq!(args.concurrency.rayon_threads, 1);
+}
+
+#[test]
+fn malformed_keys_are_reported_with_their_location() {
+    let err = error("requests = 10\n[concurrency]\nworkers = 4\n");
+    assert!(err.contains("unknown field `workers`"), "{err}");
+    assert!(err.contains("for key `concurrency` at line 2"), "{err}");
+
+    let err = error("requests = \"many\"\n");
+    assert!(err.contains("requests"), "{err}");
+
+    let err = error("modes = [\"sync\", \"threaded\"]\n");
+    assert!(err.contains("unknown variant `threaded`"), "{err}");
+}
+
+#[test]
+fn invalid_values_point_at_the_field() {
+    for (text, expected) in [
+        (
+            "endpoints = [\"devnet\", \"nowhere\"]",
+            "invalid `endpoints[1]`",
+        ),
+        ("endpoints = []", "invalid `endpoints`"),
+        ("mock = 2\nendpoints = [\"devnet\"]", "invalid `mock`"),
+        ("requests = 0", "invalid `requests`: must be at least 1"),
+        ("iterations = 0", "invalid `iterations`"),
+        ("timeout = 0", "invalid `timeout`: must be at least 1"),
+        (
+            "[concurrency]\nrayon-threads = 0",
+            "invalid `concurrency.rayon-threads`: must be at least 1",
+        ),
+        ("burst = 4", "invalid `burst`: requires `rps`"),
+        (
+            "rps = 1e-30",
+            "invalid `rps`: must be at least 0.001 requests per second",
+        ),
+        (
+            "rps = 10\n[load]\narrival-rate = 10",
+            "invalid `rps`: conflicts with `load.arrival-rate`",
+        ),
+        (
+            "[method]\naddresses = [\"not a pubkey\"]",
+            "invalid `method.addresses[0]`",
+        ),
+        (
+            "[simulate]\naccounts-encoding = \"base64\"",
+            "invalid `simulate.accounts-encoding`",
+        ),
+        ("[load]\nduration = 10", "invalid `load.duration`"),
+        (
+            "[load]\narrival-rate = 1e-30",
+            "invalid `load.arrival-rate`: must be at least 0.001",
+        ),
+        (
+            "[thresholds]\nmax-error-rate = 5",
+            "invalid `thresholds.max-error-rate`",
+        ),
+        (
+            "[faults]\ndrop = 0.5",
+            "invalid `faults.drop`: requires `mock`",
+        ),
+        (
+            "mock = 1\n[faults]\nlatency = \"gamma:2\"",
+            "invalid `faults.latency`",
+        ),
+        (
+            "mock = 1\n[faults]\ndrop = 0.5\nunavailable = 0.6",
+            "invalid `faults`: fault probabilities add up to",
+        ),
+    ] {
+        let err = error(text);
+        assert!(err.starts_with(expected), "{text:?}: {err}");
+    }
+}
+
+#[test]
+fn scenario_runs_and_checks_thresholds() {
+    let server = mock();
+    let mut args = parse(
+        r#"
+        modes = ["async"]
+        requests = 10
+
+        [method]
+        name = "getSlot"
+
+        [thresholds]
+        max-p50-us = 60000000
+        min-rps = 1000000000
+        "#,
+    )
+    .unwrap();
+    args.urls = vec![server.url()];
+
+    let runs = BenchmarkBuilder::from_args(&args).unwrap().run().unwrap();
+    let violations = threshold::check(&args.thresholds, &runs);
+    assert_eq!(
+        violations
+            .iter()
+            .map(|violation| violation.threshold)
+            .collect::<Vec<_>>(),
+        ["min-rps"]
+    );
+}
+
+#[test]
+fn scenario_starts_its_mocks_and_sustains_the_arrival_rate() {
+    let args = parse(
+        r#"
+        mock = 2
+        modes = ["raw"]
+
+        [method]
+        name = "getSlot"
+
+        [load]
+        arrival-rate = 100.0
+        duration = 0.1
+        "#,
+    )
+    .unwrap();
+
+    let benchmark = BenchmarkBuilder::from_args(&args).unwrap().build().unwrap();
+    assert_eq!(benchmark.requests(), 10);
+    let endpoints = benchmark.endpoints().to_vec();
+    assert_eq!(endpoints.len(), 2);
+    let runs = benchmark.run();
+    assert_eq!(
+        runs.iter().map(|run| &run.endpoint).collect::<Vec<_>>(),
+        endpoints.iter().collect::<Vec<_>>()
+    );
+    for run in &runs {
+        assert_eq!(run.stats.latencies.len(), 10, "{}", run.endpoint);
+        assert_eq!(run.stats.errors, 0, "{}", run.endpoint);
+    }
+}
+
+#[test]
+fn args_without_endpoints_are_rejected() {
+    let mut args = parse("[workload]\nkind = \"transfer\"").unwrap();
+    args.urls.clear();
+    let err = BenchmarkBuilder::from_args(&args).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
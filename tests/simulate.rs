@@ -0,0 +1,151 @@
+mod common;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use common::{mock, MODES};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{
+    RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
+use solana_sdk::{
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use solana_transaction_status::UiTransactionEncoding;
+use tx_sim::{
+    mock_server::{MockServer, BASE_SLOT},
+    outcome::{Expect, Outcome},
+    rpc_error::{INVALID_PARAMS, MIN_CONTEXT_SLOT_NOT_REACHED, SIGNATURE_VERIFICATION_FAILURE},
+    workload::{Workload, WorkloadKind},
+    Benchmark,
+};
+
+/// Transfers whose signature slot is left empty, so they sanitize but don't
+/// verify
+struct Unsigned(Keypair);
+
+impl Workload for Unsigned {
+    fn build(&self) -> Transaction {
+        let payer = self.0.pubkey();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        Transaction::new_unsigned(Message::new(&[transfer], Some(&payer)))
+    }
+}
+
+fn transfer() -> Arc<dyn Workload> {
+    Arc::from(WorkloadKind::Transfer.into_workload(Keypair::new(), 1, Hash::new_unique()))
+}
+
+/// Simulates `workload` with `config` in every mode, checking each request
+/// ends in `outcome`
+fn assert_outcome(
+    server: &MockServer,
+    workload: Arc<dyn Workload>,
+    config: RpcSimulateTransactionConfig,
+    outcome: Outcome,
+) {
+    let builder = Benchmark::builder()
+        .endpoint(server.url())
+        .modes(MODES)
+        .workload(workload)
+        .simulate(config.clone())
+        .requests(8);
+    let builder = match outcome {
+        Outcome::RpcError(code) => builder.expect_code(code),
+        _ => builder.expect(Expect::Success),
+    };
+    for run in builder.run().unwrap() {
+        assert_eq!(
+            run.stats.outcomes,
+            BTreeMap::from([(outcome, 8)]),
+            "{:?} {config:?}",
+            run.mode
+        );
+        assert_eq!(run.stats.unexpected, 0);
+    }
+}
+
+#[test]
+fn sig_verify_checks_signatures() {
+    let server = mock();
+    let sig_verify = RpcSimulateTransactionConfig {
+        sig_verify: true,
+        ..RpcSimulateTransactionConfig::default()
+    };
+    assert_outcome(&server, transfer(), sig_verify.clone(), Outcome::Success);
+    assert_outcome(
+        &server,
+        Arc::new(Unsigned(Keypair::new())),
+        sig_verify,
+        Outcome::RpcError(SIGNATURE_VERIFICATION_FAILURE),
+    );
+    assert_outcome(
+        &server,
+        Arc::new(Unsigned(Keypair::new())),
+        RpcSimulateTransactionConfig::default(),
+        Outcome::Success,
+    );
+}
+
+#[test]
+fn sig_verify_conflicts_with_replace_recent_blockhash() {
+    let server = mock();
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: true,
+        replace_recent_blockhash: true,
+        ..RpcSimulateTransactionConfig::default()
+    };
+    assert_outcome(
+        &server,
+        transfer(),
+        config,
+        Outcome::RpcError(INVALID_PARAMS),
+    );
+}
+
+#[test]
+fn min_context_slot_must_be_reached() {
+    let server = mock();
+    let config = |slot| RpcSimulateTransactionConfig {
+        min_context_slot: Some(slot),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    assert_outcome(&server, transfer(), config(BASE_SLOT), Outcome::Success);
+    assert_outcome(
+        &server,
+        transfer(),
+        config(u64::MAX),
+        Outcome::RpcError(MIN_CONTEXT_SLOT_NOT_REACHED),
+    );
+}
+
+#[test]
+fn transactions_are_sent_in_either_encoding() {
+    let server = mock();
+    for encoding in [UiTransactionEncoding::Base58, UiTransactionEncoding::Base64] {
+        let config = RpcSimulateTransactionConfig {
+            encoding: Some(encoding),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        assert_outcome(&server, transfer(), config, Outcome::Success);
+    }
+}
+
+#[test]
+fn accounts_can_be_requested_after_simulation() {
+    let server = mock();
+    for encoding in [None, Some(UiAccountEncoding::Base64)] {
+        let config = RpcSimulateTransactionConfig {
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding,
+                addresses: vec![Pubkey::new_unique().to_string(); 2],
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        assert_outcome(&server, transfer(), config, Outcome::Success);
+    }
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::mock;
+use serde_json::Value;
+use tx_sim::{operation::Method, trace::ChromeLayer, Benchmark, Mode};
+
+// The subscriber is global, so every traced run shares this one test
+#[test]
+fn trace_shows_request_phases_per_thread_and_task() {
+    let trace = ChromeLayer::install().unwrap();
+    assert!(ChromeLayer::install().is_err());
+
+    let server = mock();
+    Benchmark::builder()
+        .endpoint(server.url())
+        .modes([Mode::Sync, Mode::Async, Mode::Raw, Mode::Batch])
+        .method(Method::SimulateTransaction)
+        .requests(8)
+        .batch_size(4)
+        .run()
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!("tx_sim-{}-trace.json", std::process::id()));
+    trace.write(&path).unwrap();
+    let json: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).ok();
+    let events = json["traceEvents"].as_array().unwrap();
+
+    let spans = events
+        .iter()
+        .filter(|event| event["ph"] == "X")
+        .collect::<Vec<_>>();
+    assert_eq!(spans.len(), trace.spans());
+    let names = spans
+        .iter()
+        .map(|span| span["name"].as_str().unwrap())
+        .collect::<BTreeSet<_>>();
+    for name in [
+        "mode",
+        "request",
+        "batch",
+        "build",
+        "rpc_client",
+        "serialize",
+        "send",
+        "await",
+        "deserialize",
+        "decode",
+    ] {
+        assert!(names.contains(name), "no {name} span in {names:?}");
+    }
+    // 8 requests in each of the sync, async and raw modes
+    let requests = spans.iter().filter(|span| span["name"] == "request");
+    assert_eq!(requests.count(), 24);
+
+    let tracks = events
+        .iter()
+        .filter(|event| event["ph"] == "M")
+        .map(|event| event["args"]["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(tracks.contains(&"rayon-0"), "{tracks:?}");
+    assert!(tracks.contains(&"task lane 0"), "{tracks:?}");
+    let track_names = events
+        .iter()
+        .filter(|event| event["ph"] == "M")
+        .map(|event| {
+            let name = event["args"]["name"].as_str().unwrap();
+            (event["tid"].as_u64().unwrap(), name)
+        })
+        .collect::<BTreeMap<_, _>>();
+    for span in &spans {
+        assert!(track_names.contains_key(&tid(span)), "{span}");
+    }
+
+    // Every phase, in every mode, happens within a request or batch on the
+    // same track
+    let mut phase_tracks = BTreeSet::new();
+    for phase in spans
+        .iter()
+        .filter(|span| PHASES.contains(&span["name"].as_str().unwrap()))
+    {
+        assert!(
+            spans.iter().any(|parent| {
+                (parent["name"] == "request" || parent["name"] == "batch")
+                    && tid(parent) == tid(phase)
+                    && within(phase, parent)
+            }),
+            "{phase} isn't within a request"
+        );
+        phase_tracks.insert(track_names[&tid(phase)]);
+    }
+    // The sync rpc client sends on the rayon threads, the async clients in
+    // tokio tasks
+    assert!(phase_tracks.contains("rayon-0"), "{phase_tracks:?}");
+    assert!(
+        phase_tracks
+            .iter()
+            .any(|track| track.starts_with("task lane")),
+        "{phase_tracks:?}"
+    );
+}
+
+const PHASES: [&str; 6] = [
+    "build",
+    "rpc_client",
+    "serialize",
+    "send",
+    "await",
+    "deserialize",
+];
+
+fn tid(span: &Value) -> u64 {
+    span["tid"].as_u64().unwrap()
+}
+
+/// Whether `span` starts and ends within `parent`, allowing for rounding
+fn within(span: &Value, parent: &Value) -> bool {
+    let (start, dur) = (span["ts"].as_f64().unwrap(), span["dur"].as_f64().unwrap());
+    let (parent_start, parent_dur) = (
+        parent["ts"].as_f64().unwrap(),
+        parent["dur"].as_f64().unwrap(),
+    );
+    start >= parent_start - 1.0 && start + dur <= parent_start + parent_dur + 1.0
+}